@@ -0,0 +1,396 @@
+// settings: defaults, overridden by the config file, overridden by the command line
+//
+// config file, toml, by default ~/.config/rpokerhud/config.toml:
+//   history_dirs = [
+//       "/home/me/.wine/drive_c/users/me/AppData/Local/PokerStars/HandHistory/me",
+//       { path = "/home/me/archive/alt", recursive = true, hero = "alt" },
+//   ]
+//   database = "/home/me/pokerhud_dbase.db"
+//   sqlite = "/home/me/pokerhud_hands.sqlite"
+//   refresh_rate = 6
+//   table_timeout = 200
+//   hero = "me"
+//   stats = ["vpi", "pfr", "3B", "No", "vpi:BTN"]
+
+use crate::stats::{Stat, K_COLUMNS};
+use serde::{Deserialize, Deserializer};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const K_REFRESH_RATE: u64 = 6; // time between two scans of history dirs in sec, appends are seen right away when watched
+const K_TIME_TO_IGNORE_TABLE: u64 = 20000; // time in sec before table is considered closed
+const K_SAVE_INTERVAL: u64 = 600; // time between two disk saves of the database file in sec
+const K_DB_BACKUPS: usize = 5; // number of previous database files kept
+const K_DATBASE_FILE: &str = "pokerhud_dbase.db";
+// hud columns shown by default, among stats::K_COLUMNS
+const K_STATS: [&str; 11] = [
+    "vpi", "pfr", "3B", "No", "F3B", "ST", "FS", "CB", "FCB", "CR", "Dk",
+];
+
+const K_USAGE: &str = "usage: rpokerhud [options]
+       rpokerhud import [options] PATH..   add hand histories, directories or zip archives to the database
+       rpokerhud rebuild [options] [PATH..]   count stats again from all hands, of PATH.. or
+                                              else of the sqlite database or else of the history dirs
+  --config FILE          config file, default ~/.config/rpokerhud/config.toml
+  --history-dir DIR      hand history directory, can be repeated
+  --recursive            also read subdirectories of the --history-dir directories
+  --database FILE        database file, default pokerhud_dbase.db
+  --sqlite FILE          also keep every hand in this sqlite database (sqlite feature)
+  --refresh-rate SECS    time between two scans of history dirs
+  --table-timeout SECS   time without new hands before a table is considered closed
+  --save-interval SECS   time between two saves of the database
+  --backups N            number of previous database files kept
+  --hero NAME            your player name, listed first, for directories without their own hero
+  --stats A,B,..         displayed stats, STAT:POS for one position (UTG,MP,CO,BTN,SB,BB), among";
+
+// a hand history directory, and whose account's histories it holds
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryDir {
+    pub path: PathBuf,
+    pub recursive: bool,      // subdirectories too
+    pub hero: Option<String>, // None: config hero
+}
+
+// in the config file, a directory is a plain path or a table with options
+#[derive(Deserialize)]
+#[serde(
+    untagged,
+    expecting = "a directory path, or { path = .., recursive = .., hero = .. }"
+)]
+enum HistoryDirEntry {
+    Path(PathBuf),
+    Dir(HistoryDir),
+}
+
+fn history_dirs<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<HistoryDir>, D::Error> {
+    let entries = Vec::<HistoryDirEntry>::deserialize(deserializer)?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| match entry {
+            HistoryDirEntry::Path(path) => HistoryDir {
+                path,
+                ..Default::default()
+            },
+            HistoryDirEntry::Dir(dir) => dir,
+        })
+        .collect())
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub enum Command {
+    #[default]
+    Hud, // follow the tables being played
+    Import(Vec<PathBuf>),
+    Rebuild(Vec<PathBuf>), // none: from the sqlite store, else the history dirs
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    #[serde(skip)]
+    pub command: Command, // from the command line only
+    #[serde(deserialize_with = "history_dirs")]
+    pub history_dirs: Vec<HistoryDir>,
+    pub database: PathBuf,
+    pub sqlite: Option<PathBuf>, // hands, for queries, see store.rs
+    pub refresh_rate: u64,
+    pub table_timeout: u64,
+    pub save_interval: u64,
+    pub backups: usize,
+    pub hero: Option<String>, // for directories without a hero
+    pub stats: Vec<String>,   // hud columns, see stats::K_COLUMNS
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            command: Command::Hud,
+            history_dirs: Vec::new(),
+            database: PathBuf::from(K_DATBASE_FILE),
+            sqlite: None,
+            refresh_rate: K_REFRESH_RATE,
+            table_timeout: K_TIME_TO_IGNORE_TABLE,
+            save_interval: K_SAVE_INTERVAL,
+            backups: K_DB_BACKUPS,
+            hero: None,
+            stats: K_STATS.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+}
+
+fn usage() -> String {
+    format!("{} {}", K_USAGE, K_COLUMNS.join(","))
+}
+
+// ~/.config/rpokerhud/config.toml
+fn default_path() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("rpokerhud").join("config.toml"))
+}
+
+impl Config {
+    // errors are meant to be shown to the user as is
+    pub fn load() -> Result<Config, String> {
+        Config::from_args(env::args().skip(1).collect())
+    }
+
+    fn from_args(args: Vec<String>) -> Result<Config, String> {
+        // config file first, the command line overrides it
+        let explicit = args
+            .iter()
+            .position(|a| a == "--config")
+            .map(|i| args.get(i + 1).map(PathBuf::from).ok_or_else(usage))
+            .transpose()?;
+        let mut config = match explicit.clone().or_else(default_path) {
+            Some(path) if explicit.is_some() || path.exists() => {
+                let contents =
+                    fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+                toml::from_str(&contents).map_err(|e| format!("{}: {}", path.display(), e))?
+            }
+            _ => Config::default(),
+        };
+
+        let mut args = args.iter();
+        let mut dirs = Vec::new();
+        let mut recursive = false;
+        while let Some(arg) = args.next() {
+            if arg == "--help" || arg == "-h" {
+                return Err(usage());
+            }
+            if arg == "--recursive" {
+                recursive = true;
+                continue;
+            }
+            if !arg.starts_with('-') {
+                match &mut config.command {
+                    Command::Import(paths) | Command::Rebuild(paths) => {
+                        paths.push(PathBuf::from(arg))
+                    }
+                    Command::Hud if arg == "import" => config.command = Command::Import(Vec::new()),
+                    Command::Hud if arg == "rebuild" => {
+                        config.command = Command::Rebuild(Vec::new())
+                    }
+                    Command::Hud => return Err(format!("unknown command {}\n{}", arg, usage())),
+                }
+                continue;
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value for {}\n{}", arg, usage()))?;
+            let number = || format!("{} expects a number, got {}", arg, value);
+            match arg.as_str() {
+                "--config" => (),
+                "--history-dir" => dirs.push(HistoryDir {
+                    path: PathBuf::from(value),
+                    ..Default::default()
+                }),
+                "--database" => config.database = PathBuf::from(value),
+                "--sqlite" => config.sqlite = Some(PathBuf::from(value)),
+                "--refresh-rate" => config.refresh_rate = value.parse().map_err(|_| number())?,
+                "--table-timeout" => config.table_timeout = value.parse().map_err(|_| number())?,
+                "--save-interval" => config.save_interval = value.parse().map_err(|_| number())?,
+                "--backups" => config.backups = value.parse().map_err(|_| number())?,
+                "--hero" => config.hero = Some(value.to_string()),
+                "--stats" => {
+                    config.stats = value.split(',').map(|s| s.trim().to_string()).collect()
+                }
+                _ => return Err(format!("unknown option {}\n{}", arg, usage())),
+            }
+        }
+        if !dirs.is_empty() {
+            for dir in &mut dirs {
+                dir.recursive = recursive;
+            }
+            config.history_dirs = dirs;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&mut self) -> Result<(), String> {
+        match &self.command {
+            Command::Import(paths) if paths.is_empty() => {
+                return Err(format!("nothing to import\n{}", usage()));
+            }
+            Command::Rebuild(paths)
+                if paths.is_empty() && self.sqlite.is_none() && self.history_dirs.is_empty() =>
+            {
+                return Err(format!(
+                    "nothing to rebuild from, give hand history paths or set sqlite or history_dirs\n{}",
+                    usage()
+                ));
+            }
+            Command::Import(paths) | Command::Rebuild(paths) => {
+                if let Some(path) = paths.iter().find(|p| !p.exists()) {
+                    return Err(format!("{} does not exist", path.display()));
+                }
+            }
+            Command::Hud => {
+                if self.history_dirs.is_empty() {
+                    return Err(format!(
+                        "no hand history directory, set history_dirs in the config file or use --history-dir\n{}",
+                        usage()
+                    ));
+                }
+                // absolute like the paths of watcher events, see history_dir()
+                for dir in &mut self.history_dirs {
+                    if !dir.path.is_dir() {
+                        return Err(format!("{} is not a directory", dir.path.display()));
+                    }
+                    dir.path = dir
+                        .path
+                        .canonicalize()
+                        .map_err(|e| format!("{}: {}", dir.path.display(), e))?;
+                }
+            }
+        }
+        if self.refresh_rate == 0 {
+            return Err("refresh rate must be at least 1 sec".to_string());
+        }
+        if self.table_timeout == 0 {
+            return Err("table timeout must be at least 1 sec".to_string());
+        }
+        if self.save_interval == 0 {
+            return Err("save interval must be at least 1 sec".to_string());
+        }
+        if let Some(stat) = self
+            .stats
+            .iter()
+            .find(|s| Stat::default().column(s).is_none())
+        {
+            return Err(format!(
+                "unknown stat {}, available: {}, or STAT:POS for one position",
+                stat,
+                K_COLUMNS.join(",")
+            ));
+        }
+        Ok(())
+    }
+
+    // hero of the directory, else the default one
+    pub fn hero<'a>(&'a self, dir: &'a HistoryDir) -> Option<&'a str> {
+        dir.hero.as_deref().or(self.hero.as_deref())
+    }
+
+    // directory a file was found in, the deepest one if several match
+    pub fn history_dir(&self, path: &Path) -> Option<&HistoryDir> {
+        self.history_dirs
+            .iter()
+            .filter(|dir| match path.parent() {
+                Some(parent) if !dir.recursive => parent == dir.path,
+                _ => path.starts_with(&dir.path),
+            })
+            .max_by_key(|dir| dir.path.components().count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    // an empty config file and a history directory
+    fn setup(test: &str) -> (TempDir, String) {
+        let dir = TempDir::new(test);
+        fs::create_dir_all(dir.join("hh")).unwrap();
+        fs::write(dir.join("config.toml"), "").unwrap();
+        let hh = dir.join("hh").to_string_lossy().to_string();
+        (dir, hh)
+    }
+
+    fn load(dir: &TempDir, args: &[&str]) -> Result<Config, String> {
+        let config = dir.join("config.toml").to_string_lossy().to_string();
+        let mut all = vec!["--config".to_string(), config];
+        all.extend(args.iter().map(|a| a.to_string()));
+        Config::from_args(all)
+    }
+
+    #[test]
+    fn command_line_overrides_config_file() {
+        let (dir, hh) = setup("precedence");
+        fs::write(
+            dir.join("config.toml"),
+            format!(
+                "history_dirs = [{{ path = {:?}, hero = \"alt\" }}]\nrefresh_rate = 3\nbackups = 2\nhero = \"me\"\n",
+                hh
+            ),
+        )
+        .unwrap();
+
+        let config = load(&dir, &[]).unwrap();
+        assert_eq!((config.refresh_rate, config.backups), (3, 2));
+        assert_eq!(config.stats, K_STATS);
+        assert_eq!(config.save_interval, K_SAVE_INTERVAL);
+        assert_eq!(config.history_dirs.len(), 1);
+        assert!(!config.history_dirs[0].recursive);
+        assert_eq!(config.hero(&config.history_dirs[0]), Some("alt"));
+
+        let config = load(&dir, &["--refresh-rate", "9", "--hero", "you"]).unwrap();
+        assert_eq!((config.refresh_rate, config.backups), (9, 2));
+        assert_eq!(config.hero.as_deref(), Some("you"));
+
+        // --history-dir replaces the directories of the config file
+        let config = load(&dir, &["--recursive", "--history-dir", &hh]).unwrap();
+        assert_eq!(config.history_dirs.len(), 1);
+        assert!(config.history_dirs[0].recursive);
+        assert_eq!(config.hero(&config.history_dirs[0]), Some("me"));
+        assert!(config
+            .history_dir(
+                &dir.path()
+                    .canonicalize()
+                    .unwrap()
+                    .join("hh")
+                    .join("sub")
+                    .join("HH1.txt")
+            )
+            .is_some());
+
+        // relative directories are made absolute, like the paths the watcher sends
+        let config = load(&dir, &["--history-dir", "src"]).unwrap();
+        let cwd = env::current_dir().unwrap();
+        assert!(config.history_dirs[0].path.is_absolute());
+        assert!(config
+            .history_dir(&cwd.join("src").join("HH1.txt"))
+            .is_some());
+    }
+
+    #[test]
+    fn validation_errors() {
+        let (dir, hh) = setup("validation");
+        let err = |args: &[&str]| {
+            let args: Vec<&str> = ["--history-dir", &hh].iter().chain(args).copied().collect();
+            load(&dir, &args).unwrap_err()
+        };
+        assert!(load(&dir, &["--history-dir", &hh]).is_ok());
+        assert!(load(&dir, &[])
+            .unwrap_err()
+            .starts_with("no hand history directory"));
+        assert!(load(&dir, &["--history-dir", "/nonexistent"])
+            .unwrap_err()
+            .ends_with("is not a directory"));
+        assert!(err(&["import"]).starts_with("nothing to import"));
+        assert!(err(&["import", "/nonexistent"]).ends_with("does not exist"));
+        assert!(load(&dir, &["rebuild"])
+            .unwrap_err()
+            .starts_with("nothing to rebuild from"));
+        assert!(err(&["--refresh-rate", "0"]).starts_with("refresh rate"));
+        assert!(err(&["--table-timeout", "0"]).starts_with("table timeout"));
+        assert!(err(&["--save-interval", "0"]).starts_with("save interval"));
+        assert!(err(&["--save-interval", "soon"]).contains("expects a number"));
+        assert!(err(&["--stats", "vpi,xyz"]).starts_with("unknown stat xyz"));
+        assert!(err(&["--stats", "vpi:XYZ"]).starts_with("unknown stat vpi:XYZ"));
+        assert!(load(&dir, &["--history-dir", &hh, "--stats", "vpi:BTN, pfr"]).is_ok());
+        assert!(err(&["--frobnicate", "1"]).starts_with("unknown option"));
+
+        fs::write(dir.join("config.toml"), "refresh = 3\n").unwrap();
+        assert!(load(&dir, &["--history-dir", &hh])
+            .unwrap_err()
+            .contains("unknown field"));
+    }
+}
@@ -0,0 +1,225 @@
+// on disk database: player stats, plus what is needed to never count a hand twice
+//
+// file: K_MAGIC, version as a little endian u32, then the database as zlib compressed messagepack.
+// fields are stored by name, new ones can be added without a new version as long as they have a default.
+// the version changes when stored data means something else, upgrade() converts older databases.
+// version 0 was json, without a header
+
+use crate::files::Files;
+use crate::hand::Hand;
+use crate::stats::{Stats, K_STATS_VERSION};
+use crate::store::Store;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
+const K_MAGIC: &[u8] = b"RPHUDDB\0";
+const K_VERSION: u32 = 1;
+
+#[derive(Default, Serialize, Deserialize, Debug)]
+pub struct Database {
+    pub stats: Stats,
+    pub hands: HashSet<u64>, // PokerStars numbers of the hands already in stats
+    #[serde(default)]
+    pub files: Files, // read offsets of hand history files
+    #[serde(default)]
+    pub stats_version: u32, // stats::K_STATS_VERSION the stats were counted with, 0 if unknown
+    #[serde(skip)]
+    pub store: Option<Store>, // every hand, if the sqlite setting is set
+}
+
+impl Database {
+    // adds the hand to stats, returns false if it was already counted
+    pub fn populate(&mut self, hand: &Hand) -> bool {
+        if !self.hands.insert(hand.id) {
+            return false;
+        }
+        self.stats.populate(hand);
+        if let Some(store) = &mut self.store {
+            if let Err(e) = store.insert([hand]) {
+                eprintln!("{}", e);
+            }
+        }
+        true
+    }
+
+    // counted with other definitions than the current ones
+    pub fn is_stale(&self) -> bool {
+        self.stats_version != K_STATS_VERSION
+    }
+
+    // empty database if there's no file yet
+    pub fn load(path: &str) -> io::Result<Database> {
+        let path = Path::new(path);
+        if !path.exists() {
+            // the database used to be a json file
+            let json = path.with_extension("json");
+            if json != path && json.exists() {
+                eprintln!("migrating {} to {}", json.display(), path.display());
+                return Database::read(&json);
+            }
+            return Ok(Database {
+                stats_version: K_STATS_VERSION,
+                ..Default::default()
+            });
+        }
+        Database::read(path)
+    }
+
+    fn read(path: &Path) -> io::Result<Database> {
+        let bytes = fs::read(path)?;
+        let invalid = |e: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        };
+        let (version, db) = match bytes.strip_prefix(K_MAGIC) {
+            Some(rest) if rest.len() >= 4 => {
+                let version = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]);
+                if version > K_VERSION {
+                    return Err(invalid(format!(
+                        "database version {} is newer than this program's {}",
+                        version, K_VERSION
+                    )));
+                }
+                let db = rmp_serde::from_read(ZlibDecoder::new(&rest[4..]))
+                    .map_err(|e| invalid(e.to_string()))?;
+                (version, db)
+            }
+            Some(_) => return Err(invalid("truncated database header".to_string())),
+            None => (0, Database::from_json(&bytes).map_err(invalid)?),
+        };
+        if version < K_VERSION {
+            eprintln!(
+                "{}: upgrading database from version {} to {}",
+                path.display(),
+                version,
+                K_VERSION
+            );
+        }
+        Ok(db.upgrade(version))
+    }
+
+    // version 0, json
+    fn from_json(bytes: &[u8]) -> Result<Database, String> {
+        match serde_json::from_slice(bytes) {
+            Ok(db) => Ok(db),
+            // first format was the bare stats, without hand numbers
+            Err(e) => match serde_json::from_slice::<Stats>(bytes) {
+                Ok(stats) => Ok(Database {
+                    stats,
+                    ..Default::default()
+                }),
+                Err(_) => Err(e.to_string()),
+            },
+        }
+    }
+
+    // converts a database read from an older version
+    fn upgrade(self, version: u32) -> Database {
+        match version {
+            // 0 -> 1: same contents, only the encoding changed
+            0 | 1 => self,
+            _ => unreachable!("newer versions are refused when reading"),
+        }
+    }
+
+    // written to a temporary file first, then renamed over the database file,
+    // so a crash while saving leaves the previous database intact.
+    // the previous database becomes path.1, path.1 becomes path.2 etc up to path.backups
+    pub fn save(&self, path: &str, backups: usize) -> io::Result<()> {
+        let tmp = format!("{}.tmp", path);
+        let mut file = fs::File::create(&tmp)?;
+        let mut writer = io::BufWriter::new(&mut file);
+        writer.write_all(K_MAGIC)?;
+        writer.write_all(&K_VERSION.to_le_bytes())?;
+        let mut encoder = ZlibEncoder::new(writer, Compression::default());
+        rmp_serde::encode::write_named(&mut encoder, self).map_err(io::Error::other)?;
+        encoder.finish()?.flush()?;
+        file.sync_all()?;
+
+        if backups > 0 && Path::new(path).exists() {
+            for i in (1..backups).rev() {
+                let older = format!("{}.{}", path, i);
+                if Path::new(&older).exists() {
+                    fs::rename(&older, format!("{}.{}", path, i + 1))?;
+                }
+            }
+            let backup = format!("{}.1", path);
+            if fs::hard_link(path, &backup).is_err() {
+                fs::copy(path, &backup)?;
+            }
+        }
+        fs::rename(&tmp, path)?;
+
+        // a failure there doesn't lose anything, counters are rewritten at the next save
+        if let Some(store) = &self.store {
+            if let Err(e) = store.save_stats(&self.stats) {
+                eprintln!("{}", e);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    #[test]
+    fn binary_round_trip_and_json_migration() {
+        let mut db = Database::default();
+        db.hands.insert(42);
+        db.stats.0.entry("bob".to_string()).or_default().handsNo = 3;
+
+        let dir = TempDir::new("migration");
+        let json = dir.join("db.json");
+        fs::write(&json, serde_json::to_string(&db).unwrap()).unwrap();
+        let path = dir.join("db.db").to_string_lossy().to_string();
+        let migrated = Database::load(&path).unwrap();
+        assert_eq!(migrated.hands, db.hands);
+        assert_eq!(migrated.stats.0["bob"].handsNo, 3);
+        assert!(migrated.is_stale()); // the json database had no stats version
+
+        migrated.save(&path, 0).unwrap();
+        assert!(fs::read(&path).unwrap().starts_with(K_MAGIC));
+        let loaded = Database::load(&path).unwrap();
+        assert_eq!(loaded.hands, db.hands);
+        assert_eq!(loaded.stats.0["bob"].handsNo, 3);
+
+        // newer versions are refused
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[K_MAGIC.len()] = K_VERSION as u8 + 1;
+        fs::write(&path, bytes).unwrap();
+        assert_eq!(
+            Database::load(&path).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn backups_are_rotated() {
+        let dir = TempDir::new("backups");
+        let path = dir.join("db.db").to_string_lossy().to_string();
+        let mut db = Database::default();
+        for hands in 1..=5 {
+            db.stats.0.entry("bob".to_string()).or_default().handsNo = hands;
+            db.save(&path, 3).unwrap();
+        }
+        let hands = |path: &str| Database::load(path).unwrap().stats.0["bob"].handsNo;
+        assert_eq!(hands(&path), 5);
+        for i in 1..=3 {
+            assert_eq!(hands(&format!("{}.{}", path, i)), 5 - i);
+        }
+        assert!(!Path::new(&format!("{}.4", path)).exists());
+        assert!(!Path::new(&format!("{}.tmp", path)).exists());
+    }
+}
@@ -0,0 +1,254 @@
+// hand history files (one per table) and how far they have been read
+// saved with the database so a restart carries on where it stopped
+
+use crate::hand::Hand;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::prelude::*;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+const K_LINE_SEPARATOR: &str = "\r\n\r\n\r\n\r\n";
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct File {
+    #[serde(skip)]
+    pub is_active: bool, // table still open
+    pub offset: u64,          // bytes already processed
+    pub size: u64,            // at last read
+    pub modified: u64,        // at last read, secs since epoch
+    pub inode: u64,           // to notice a file replaced by another one with the same name
+    pub players: Vec<String>, // players in latest hand, ie need stats printed
+    #[serde(skip)]
+    pub hero: Option<String>, // account the file belongs to, from its directory
+    #[serde(skip)]
+    pub handle: Option<fs::File>, // kept open while the table is active
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct Files(pub HashMap<String, File>); // key is the file path
+
+#[cfg(unix)]
+fn inode(metadata: &fs::Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::ino(metadata)
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &fs::Metadata) -> u64 {
+    0
+}
+
+// cut hand history into strings of hands
+pub fn split_hands(contents: &str) -> Vec<&str> {
+    contents
+        .split(K_LINE_SEPARATOR)
+        .filter(|s| !s.trim().is_empty())
+        .collect()
+}
+
+// files of a directory, and of all its subdirectories if recursive
+pub fn list(dir: &Path, recursive: bool) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(t) if t.is_dir() && recursive => paths.extend(list(&path, true)),
+            Ok(t) if t.is_file() => paths.push(path),
+            _ => (),
+        }
+    }
+    paths
+}
+
+impl File {
+    // start again from the beginning if the file shrank or was replaced since last read,
+    // hands already counted are skipped by their hand number
+    pub fn check(&mut self, metadata: &fs::Metadata) {
+        let inode = inode(metadata);
+        if metadata.len() < self.size || (self.inode != 0 && inode != self.inode) {
+            self.offset = 0;
+            self.handle = None;
+        }
+        self.size = metadata.len();
+        self.inode = inode;
+        self.modified = metadata
+            .modified()
+            .ok()
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs());
+    }
+
+    // bytes appended since offset, offset is moved to the end of what was read
+    // returns the previous offset too, None if nothing new
+    pub fn read_new(&mut self, path: &Path) -> Option<(u64, Vec<u8>)> {
+        // the file stays open while the table is active
+        if self.handle.is_none() {
+            self.handle = Some(fs::File::open(path).ok()?);
+        }
+        let file = self.handle.as_mut()?;
+        let end = file.seek(SeekFrom::End(0)).ok()?;
+        if end <= self.offset {
+            return None;
+        }
+        // goto previous offset, read only up to the end seen above
+        file.seek(SeekFrom::Start(self.offset)).ok()?;
+        let mut bytes = Vec::new();
+        file.take(end - self.offset).read_to_end(&mut bytes).ok()?;
+        // stopped in the middle of a character, the rest is read next time.
+        // other invalid bytes are kept, they're replaced when decoded
+        let mut rest = bytes.as_slice();
+        while let Err(e) = std::str::from_utf8(rest) {
+            match e.error_len() {
+                Some(len) => rest = &rest[e.valid_up_to() + len..],
+                None => {
+                    let cut = rest.len() - e.valid_up_to();
+                    bytes.truncate(bytes.len() - cut);
+                    break;
+                }
+            }
+        }
+        if bytes.is_empty() {
+            return None;
+        }
+        let start = self.offset;
+        self.offset += bytes.len() as u64;
+        Some((start, bytes))
+    }
+
+    // complete hands appended since last read, also updates players
+    pub fn read_hands(&mut self, path: &Path) -> Vec<Hand> {
+        let (start, bytes) = match self.read_new(path) {
+            Some(new) => new,
+            None => return Vec::new(),
+        };
+        // invalid characters are replaced like import does
+        let contents = String::from_utf8_lossy(&bytes);
+        let mut texts = split_hands(&contents);
+        // hands are followed by the separator once written, text after the last one is a hand
+        // still being written and can be cut anywhere, even in its summary where it would parse.
+        // it is read again next time
+        let sep = K_LINE_SEPARATOR.as_bytes();
+        let tail = bytes
+            .windows(sep.len())
+            .rposition(|w| w == sep)
+            .map_or(0, |p| p + sep.len());
+        if !String::from_utf8_lossy(&bytes[tail..]).trim().is_empty() {
+            texts.pop();
+            self.offset = start + tail as u64;
+        }
+
+        let mut hands = Vec::new();
+        for text in texts {
+            match Hand::parse(text) {
+                Ok(hand) => hands.push(hand),
+                Err(e) => eprintln!("{}: skipping hand, {}", path.display(), e),
+            }
+        }
+
+        // update active players
+        if let Some(hand) = hands.last() {
+            self.players = hand.players();
+        }
+        hands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    const K_HAND: &str =
+        "PokerStars Hand #1:  Hold'em No Limit ($0.01/$0.02 USD) - 2020/05/30 10:11:12 ET\r\n\
+        Table 'Test' 6-max Seat #1 is the button\r\n\
+        Seat 1: p1 ($2 in chips)\r\nSeat 2: p2 ($2 in chips)\r\n\
+        p1: posts small blind $0.01\r\np2: posts big blind $0.02\r\n*** HOLE CARDS ***\r\n\
+        p1: raises $0.04 to $0.06\r\np2: folds\r\nUncalled bet ($0.04) returned to p1\r\n\
+        p1 collected $0.03 from pot\r\n*** SUMMARY ***\r\nTotal pot $0.04 | Rake $0.01";
+
+    fn append(path: &Path, text: &str) {
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn hand_cut_anywhere_is_read_again() {
+        let dir = TempDir::new("cut");
+        let path = dir.join("cut.txt");
+        let second = K_HAND.replace("Hand #1", "Hand #2");
+        append(&path, K_HAND);
+        append(&path, K_LINE_SEPARATOR);
+        // cut in the middle of an action line, not a truncated hand to the parser
+        let cut = second.find("p1: raises").unwrap() + "p1: ra".len();
+        append(&path, &second[..cut]);
+
+        let mut file = File::default();
+        let hands = file.read_hands(&path);
+        assert_eq!(hands.iter().map(|h| h.id).collect::<Vec<_>>(), [1]);
+        assert_eq!(file.offset, (K_HAND.len() + K_LINE_SEPARATOR.len()) as u64);
+
+        // cut in the summary, it would parse without its pot and rake
+        let summary = second.find("Total pot $0.0").unwrap() + "Total pot $0.0".len();
+        append(&path, &second[cut..summary]);
+        assert!(file.read_hands(&path).is_empty());
+        assert_eq!(file.offset, (K_HAND.len() + K_LINE_SEPARATOR.len()) as u64);
+
+        append(&path, &second[summary..]);
+        assert!(file.read_hands(&path).is_empty());
+        append(&path, K_LINE_SEPARATOR);
+        let hands = file.read_hands(&path);
+        assert_eq!(hands.iter().map(|h| h.id).collect::<Vec<_>>(), [2]);
+        assert_eq!((hands[0].pot, hands[0].rake), (4, 1));
+
+        // once followed by the separator, a bad hand is skipped for good
+        append(&path, &K_HAND.replace("p2: folds", "p2: dances"));
+        append(&path, K_LINE_SEPARATOR);
+        assert!(file.read_hands(&path).is_empty());
+        assert_eq!(file.offset, fs::metadata(&path).unwrap().len());
+    }
+
+    #[test]
+    fn offset_reset_when_shrunk_or_replaced() {
+        let dir = TempDir::new("check");
+        let path = dir.join("check.txt");
+        let mut file = File::default();
+        let check = |file: &mut File| file.check(&fs::metadata(&path).unwrap());
+        fs::write(&path, "abc").unwrap();
+        check(&mut file);
+        file.offset = 3;
+        append(&path, "def");
+        check(&mut file);
+        assert_eq!((file.offset, file.size), (3, 6));
+
+        fs::write(&path, "ab").unwrap();
+        check(&mut file);
+        assert_eq!((file.offset, file.size), (0, 2));
+
+        // same name, bigger, but another file
+        file.offset = 2;
+        let other = path.with_extension("new");
+        fs::write(&other, "abcdefgh").unwrap();
+        fs::rename(&other, &path).unwrap();
+        check(&mut file);
+        assert_eq!(file.offset, if cfg!(unix) { 0 } else { 2 });
+    }
+
+    #[test]
+    fn only_a_cut_character_is_read_again() {
+        let dir = TempDir::new("utf8");
+        let path = dir.join("utf8.txt");
+        fs::write(&path, b"a\xffb\xc3").unwrap();
+        let mut file = File::default();
+        assert_eq!(file.read_new(&path), Some((0, b"a\xffb".to_vec())));
+        assert_eq!(file.read_new(&path), None);
+
+        fs::write(&path, b"a\xffb\xc3\xa9").unwrap();
+        assert_eq!(file.read_new(&path), Some((3, "é".into())));
+    }
+}
@@ -0,0 +1,1119 @@
+// hand history model
+// one Hand per "PokerStars Hand #..." block, stats are derived from it (see stats.rs)
+// the model carries more than the current stats use
+
+use std::collections::HashMap;
+use std::fmt;
+
+pub type Chips = i64; // amounts in hundredths: cents for cash games, chips * 100 for tournaments
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stakes {
+    pub sb: Chips,
+    pub bb: Chips,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Seat {
+    pub seat: u8,
+    pub name: String,
+    pub stack: Chips,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostKind {
+    SmallBlind,
+    BigBlind,
+    SmallAndBig, // dead blinds when coming back in
+    Ante,
+}
+
+#[derive(Debug, Clone)]
+pub struct Post {
+    pub name: String,
+    pub kind: PostKind,
+    pub amount: Chips,
+    #[allow(dead_code)] // no stat yet, kept in the sqlite store
+    pub is_all_in: bool,
+}
+
+// chips won, from the main pot or a side pot, or an uncalled bet given back
+#[derive(Debug, Clone)]
+pub struct Collected {
+    pub name: String,
+    pub amount: Chips,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Street {
+    Preflop,
+    Flop,
+    Turn,
+    River,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionKind {
+    Fold,
+    Check,
+    Call,
+    Bet,
+    Raise,
+}
+
+#[derive(Debug, Clone)]
+pub struct Action {
+    pub street: Street,
+    pub name: String,
+    pub kind: ActionKind,
+    pub amount: Chips, // chips put in the pot by this action, 0 for folds and checks
+    pub to: Chips,     // total bet on this street after a bet or raise, 0 otherwise
+    pub pot: Chips,    // pot before the action, blinds and antes included
+    #[allow(dead_code)] // no stat yet, kept in the sqlite store
+    pub stack: Chips, // stack left after the action
+    #[allow(dead_code)]
+    pub is_all_in: bool,
+}
+
+// preflop position. 7 to 10 handed, the first half of the seats between the big blind
+// and the cutoff are UTG and the second half MP. heads up the button is the small blind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    Utg,
+    Mp,
+    Co,
+    Btn,
+    Sb,
+    Bb,
+}
+
+impl Position {
+    pub const ALL: [Position; 6] = [
+        Position::Utg,
+        Position::Mp,
+        Position::Co,
+        Position::Btn,
+        Position::Sb,
+        Position::Bb,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Position::Utg => "UTG",
+            Position::Mp => "MP",
+            Position::Co => "CO",
+            Position::Btn => "BTN",
+            Position::Sb => "SB",
+            Position::Bb => "BB",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Money {
+    #[default]
+    Real,
+    Play,
+    Tournament, // chips
+}
+
+#[derive(Debug, Default)]
+pub struct Hand {
+    pub id: u64,      // PokerStars hand number
+    pub date: String, // "2020-05-30 04:11:12", eastern time when the history has it
+    pub game: String, // eg "Hold'em No Limit"
+    pub stakes: Stakes,
+    pub money: Money,
+    pub table: String,
+    pub max_seats: u8,
+    pub button: u8,       // seat number of the button
+    pub seats: Vec<Seat>, // in seat order
+    pub posts: Vec<Post>,
+    pub actions: Vec<Action>,      // in order of play, all streets
+    pub board: Vec<String>,        // community cards dealt, eg ["2c", "7h", "Td"]
+    pub showdown: Vec<String>,     // players who showed or mucked at showdown
+    pub collected: Vec<Collected>, // pots won, a player can win several
+    pub returned: Vec<Collected>,  // uncalled bets
+    pub pot: Chips,                // total pot, from the summary
+    pub rake: Chips,
+    pub jackpot: Chips, // taken from the pot like the rake
+}
+
+// line numbers start at 1 at the first line of the hand
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    Truncated { line: usize }, // hand ends before the summary, eg still being written
+    MissingHeader { line: usize }, // no "PokerStars Hand #" or "Table '...'" line
+    BadSeatLine { line: usize, text: String },
+    UnknownAction { line: usize, text: String },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Truncated { line } => write!(f, "hand truncated at line {}", line),
+            ParseError::MissingHeader { line } => write!(f, "missing hand header at line {}", line),
+            ParseError::BadSeatLine { line, text } => {
+                write!(f, "bad seat line {}: {}", line, text)
+            }
+            ParseError::UnknownAction { line, text } => {
+                write!(f, "unknown action line {}: {}", line, text)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// lines with a "player: " prefix that are not actions
+const K_OTHER_PLAYER_LINES: [&str; 8] = [
+    "shows ",
+    "mucks hand",
+    "doesn't show hand",
+    "is sitting out",
+    "sits out",
+    "is disconnected",
+    "is connected",
+    "has returned",
+];
+
+// "$0.25" -> 25, "1500" -> 150000
+pub fn parse_chips(s: &str) -> Option<Chips> {
+    let s = s.trim().trim_start_matches(|c: char| !c.is_ascii_digit());
+    let end = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let s = &s[..end];
+    let (units, hundredths) = match s.find('.') {
+        Some(dot) => (&s[..dot], &s[dot + 1..]),
+        None => (s, ""),
+    };
+    let units: Chips = units.parse().ok()?;
+    let hundredths: Chips = match hundredths.len() {
+        0 => 0,
+        1 => hundredths.parse::<Chips>().ok()? * 10,
+        _ => hundredths[..2].parse().ok()?,
+    };
+    Some(units * 100 + hundredths)
+}
+
+// "2020/05/30 10:11:12 CET [2020/05/30 4:11:12 ET]" -> "2020-05-30 04:11:12"
+fn parse_date(s: &str) -> Option<String> {
+    let s = match (s.find('['), s.find(']')) {
+        (Some(open), Some(close)) if open < close => &s[open + 1..close],
+        _ => s,
+    };
+    let mut tokens = s.split_whitespace();
+    let date: Vec<u32> = tokens
+        .next()?
+        .split('/')
+        .map(|n| n.parse().ok())
+        .collect::<Option<_>>()?;
+    let time: Vec<u32> = tokens
+        .next()?
+        .split(':')
+        .map(|n| n.parse().ok())
+        .collect::<Option<_>>()?;
+    match (date.as_slice(), time.as_slice()) {
+        ([y, m, d], [h, min, sec]) => Some(format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            y, m, d, h, min, sec
+        )),
+        _ => None,
+    }
+}
+
+// "*** TURN *** [2c 7h Td] [Js]" -> ["Js"], the cards dealt are in the last brackets
+fn parse_dealt(line: &str) -> Vec<String> {
+    let cards = line.rsplit('[').next().unwrap_or("");
+    let cards = cards.split(']').next().unwrap_or("");
+    cards.split_whitespace().map(|c| c.to_string()).collect()
+}
+
+impl Hand {
+    // expects a string containing one hand
+    pub fn parse(onehand: &str) -> Result<Hand, ParseError> {
+        let mut hand: Hand = Default::default();
+        let mut street = Street::Preflop;
+        let mut lines = onehand
+            .trim_start_matches(|c: char| c == '\u{feff}' || c.is_whitespace())
+            .lines()
+            .enumerate()
+            .map(|(i, l)| (i + 1, l));
+        let mut line = 0; // last line read
+        let mut has_table = false;
+        let mut done = false;
+        let mut is_showdown = false;
+
+        // running totals
+        let mut pot: Chips = 0;
+        let mut stacks: HashMap<String, Chips> = HashMap::new();
+        let mut committed: HashMap<String, Chips> = HashMap::new(); // bet by player on current street
+
+        // hand start ie prepreflop
+        for (n, tline) in &mut lines {
+            line = n;
+            if n == 1 {
+                match tline.find("Hand #") {
+                    Some(pos) if hand.parse_header(&tline[pos + 6..]) => (),
+                    _ => return Err(ParseError::MissingHeader { line }),
+                }
+                continue;
+            }
+
+            // done prepreflop
+            if tline.starts_with("*** HOLE CARDS ***") {
+                if !has_table {
+                    return Err(ParseError::MissingHeader { line });
+                }
+                done = true;
+                break;
+            }
+
+            // table name, max seats, money type and button seat number
+            if tline.starts_with("Table '") {
+                if !hand.parse_table(tline) {
+                    return Err(ParseError::MissingHeader { line });
+                }
+                has_table = true;
+                continue;
+            }
+
+            // found a player entry
+            if tline.starts_with("Seat ") && tline.contains(" in chips") {
+                let bad = || ParseError::BadSeatLine {
+                    line,
+                    text: tline.to_string(),
+                };
+                // Seat 3: name ($1.95 in chips) is sitting out
+                // the name can contain anything, the stack is in the last " (" before " in chips"
+                let start = tline.find(": ").ok_or_else(bad)? + 2;
+                let chips = tline.rfind(" in chips").ok_or_else(bad)?;
+                let paren = tline[..chips].rfind(" (").ok_or_else(bad)?;
+                let seat = Seat {
+                    seat: tline[5..start - 2].parse().map_err(|_| bad())?,
+                    name: tline.get(start..paren).ok_or_else(bad)?.to_string(),
+                    stack: parse_chips(&tline[paren + 2..]).ok_or_else(bad)?,
+                };
+                stacks.insert(seat.name.clone(), seat.stack);
+                hand.seats.push(seat);
+                continue;
+            }
+
+            if let Some((name, rest)) = hand
+                .split_name(tline)
+                .and_then(|(name, rest)| Some((name, rest.strip_prefix("posts ")?)))
+            {
+                let kind = if rest.starts_with("small & big blinds") {
+                    PostKind::SmallAndBig
+                } else if rest.starts_with("small blind") {
+                    PostKind::SmallBlind
+                } else if rest.starts_with("big blind") {
+                    PostKind::BigBlind
+                } else if rest.starts_with("the ante") {
+                    PostKind::Ante
+                } else {
+                    continue;
+                };
+                let is_all_in = rest.ends_with(" and is all-in");
+                let rest = rest.trim_end_matches(" and is all-in");
+                let amount = rest
+                    .rfind(' ')
+                    .and_then(|p| parse_chips(&rest[p..]))
+                    .unwrap_or(0);
+                // dead small blind and antes go to the pot but don't count as a bet
+                let live = match kind {
+                    PostKind::SmallBlind | PostKind::BigBlind => amount,
+                    PostKind::SmallAndBig => amount.min(hand.stakes.bb),
+                    PostKind::Ante => 0,
+                };
+                pot += amount;
+                *stacks.entry(name.to_string()).or_default() -= amount;
+                *committed.entry(name.to_string()).or_default() += live;
+                hand.posts.push(Post {
+                    name: name.to_string(),
+                    kind,
+                    amount,
+                    is_all_in,
+                });
+            }
+        }
+
+        if !done {
+            return Err(ParseError::Truncated { line });
+        }
+        done = false;
+
+        // preflop to river
+        for (n, tline) in lines {
+            line = n;
+            if tline.starts_with("*** SUMMARY ***") {
+                done = true;
+                continue;
+            }
+            if done {
+                // Total pot $0.68 | Rake $0.03 | Jackpot $0.01 | Bingo $0
+                // Total pot $10 Main pot $6. Side pot $4. | Rake $0.50
+                if tline.starts_with("Total pot ") {
+                    for part in tline.split(" | ") {
+                        let amount = || parse_chips(part).unwrap_or(0);
+                        if part.starts_with("Total pot ") {
+                            hand.pot = amount();
+                        } else if part.starts_with("Rake ") {
+                            hand.rake = amount();
+                        } else if part.starts_with("Jackpot ") {
+                            hand.jackpot = amount();
+                        }
+                    }
+                }
+                continue; // the rest of the summary repeats the hand
+            }
+            if tline.starts_with("*** FLOP ***") {
+                street = Street::Flop;
+                committed.clear();
+                hand.board.extend(parse_dealt(tline));
+                continue;
+            }
+            if tline.starts_with("*** TURN ***") {
+                street = Street::Turn;
+                committed.clear();
+                hand.board.extend(parse_dealt(tline));
+                continue;
+            }
+            if tline.starts_with("*** RIVER ***") {
+                street = Street::River;
+                committed.clear();
+                hand.board.extend(parse_dealt(tline));
+                continue;
+            }
+            if tline.starts_with("*** SHOW DOWN ***") {
+                is_showdown = true;
+                continue;
+            }
+
+            // p1 collected $0.13 from pot, also from side pot and from main pot
+            if let Some(collected) = hand.parse_collected(tline) {
+                hand.collected.push(collected);
+                continue;
+            }
+
+            // Uncalled bet ($0.04) returned to player
+            if let Some(rest) = tline.strip_prefix("Uncalled bet (") {
+                if let Some((amount, name)) = rest.split_once(") returned to ") {
+                    let amount = parse_chips(amount).unwrap_or(0);
+                    pot -= amount;
+                    *stacks.entry(name.to_string()).or_default() += amount;
+                    hand.returned.push(Collected {
+                        name: name.to_string(),
+                        amount,
+                    });
+                }
+                continue;
+            }
+
+            let (name, rest) = match hand.split_name(tline) {
+                Some(split) => split,
+                None => continue, // not a player line, or chat etc
+            };
+            let kind = if rest.starts_with("folds") {
+                ActionKind::Fold
+            } else if rest.starts_with("checks") {
+                ActionKind::Check
+            } else if rest.starts_with("calls ") {
+                ActionKind::Call
+            } else if rest.starts_with("bets ") {
+                ActionKind::Bet // don't think PS uses "bets" preflop, but just in case.
+            } else if rest.starts_with("raises ") {
+                ActionKind::Raise
+            } else if K_OTHER_PLAYER_LINES.iter().any(|o| rest.starts_with(o)) {
+                let shows = rest.starts_with("shows ") || rest.starts_with("mucks hand");
+                if is_showdown && shows && !hand.showdown.iter().any(|n| n == name) {
+                    hand.showdown.push(name.to_string());
+                }
+                continue; // not an action
+            } else {
+                return Err(ParseError::UnknownAction {
+                    line,
+                    text: tline.to_string(),
+                });
+            };
+            let is_all_in = rest.ends_with(" and is all-in");
+            let rest = rest.trim_end_matches(" and is all-in");
+            let first = rest
+                .find(' ')
+                .and_then(|p| parse_chips(&rest[p..]))
+                .unwrap_or(0);
+            let bet = committed.entry(name.to_string()).or_default();
+            // raises $0.10 to $0.25
+            let (amount, to) = match kind {
+                ActionKind::Fold | ActionKind::Check => (0, 0),
+                ActionKind::Call => (first, 0),
+                ActionKind::Bet => (first, first),
+                ActionKind::Raise => {
+                    let to = rest
+                        .rfind(" to ")
+                        .and_then(|p| parse_chips(&rest[p + 4..]))
+                        .unwrap_or(*bet + first);
+                    (to - *bet, to)
+                }
+            };
+            *bet += amount;
+            let stack = stacks.entry(name.to_string()).or_default();
+            *stack -= amount;
+            hand.actions.push(Action {
+                street,
+                name: name.to_string(),
+                kind,
+                amount,
+                to,
+                pot,
+                stack: *stack,
+                is_all_in,
+            });
+            pot += amount;
+        }
+
+        if !done {
+            return Err(ParseError::Truncated { line });
+        }
+        Ok(hand)
+    }
+
+    // rest of the first line after "Hand #", eg
+    // 213411111111:  Hold'em No Limit ($0.01/$0.02 USD) - 2020/05/30 10:11:12 CET [2020/05/30 4:11:12 ET]
+    // 213411111111: Tournament #456, $1.00+$0.10 USD Hold'em No Limit - Level I (10/20) - 2020/05/30 ...
+    // returns false if there's no hand number
+    fn parse_header(&mut self, header: &str) -> bool {
+        let (id, rest) = match header.split_once(':') {
+            Some(split) => split,
+            None => return false,
+        };
+        self.id = match id.trim().parse() {
+            Ok(id) => id,
+            Err(_) => return false,
+        };
+
+        let parts: Vec<&str> = rest.trim().split(" - ").collect();
+        let (game, stakes) = if parts[0].starts_with("Tournament #") {
+            self.money = Money::Tournament;
+            // skip tournament number, buy-in and currency
+            let mut game = parts[0].split_once(", ").map_or("", |(_, g)| g).trim();
+            while let Some((token, g)) = game.split_once(' ') {
+                let is_buyin = token.contains(|c: char| c.is_ascii_digit())
+                    || token == "Freeroll"
+                    || (token.len() == 3 && token.chars().all(|c| c.is_ascii_uppercase()));
+                if !is_buyin {
+                    break;
+                }
+                game = g.trim_start();
+            }
+            (game, parts.get(1).copied().unwrap_or(""))
+        } else {
+            (parts[0], parts[0])
+        };
+        self.game = match game.find(" (") {
+            Some(p) => game[..p].to_string(),
+            None => game.trim().to_string(),
+        };
+        if let (Some(open), Some(close)) = (stakes.find('('), stakes.find(')')) {
+            if let Some((sb, bb)) = stakes[open + 1..close].split_once('/') {
+                self.stakes = Stakes {
+                    sb: parse_chips(sb).unwrap_or(0),
+                    bb: parse_chips(bb).unwrap_or(0),
+                };
+            }
+        }
+        self.date = parts
+            .iter()
+            .rev()
+            .find_map(|p| parse_date(p))
+            .unwrap_or_default();
+        true
+    }
+
+    // Table 'Aase II' 6-max Seat #3 is the button
+    // Table 'Aase II' 6-max (Play Money) Seat #3 is the button
+    // Table '2962937846 1' 9-max Seat #1 is the button
+    // returns false if the line doesn't follow that grammar
+    fn parse_table(&mut self, tline: &str) -> bool {
+        let rest = match tline
+            .strip_prefix("Table '")
+            .and_then(|r| r.strip_suffix(" is the button"))
+        {
+            Some(rest) => rest,
+            None => return false,
+        };
+        let (rest, button) = match rest.rsplit_once(" Seat #") {
+            Some(split) => split,
+            None => return false,
+        };
+        // the table name is quoted, but may itself contain quotes
+        let (table, options) = match rest.rsplit_once('\'') {
+            Some(split) => split,
+            None => return false,
+        };
+        self.table = table.to_string();
+        self.button = match button.parse() {
+            Ok(button) => button,
+            Err(_) => return false,
+        };
+        for option in options.split_whitespace() {
+            if let Some(max) = option.strip_suffix("-max") {
+                self.max_seats = max.parse().unwrap_or(0);
+            }
+        }
+        if options.contains("(Play Money)") {
+            self.money = Money::Play;
+        }
+        true
+    }
+
+    // splits "name: rest" using the seated players as the set of possible names,
+    // the longest name wins so names containing ": " or action keywords are fine
+    pub fn split_name<'a>(&self, line: &'a str) -> Option<(&'a str, &'a str)> {
+        self.seats
+            .iter()
+            .filter(|s| line.starts_with(&s.name) && line[s.name.len()..].starts_with(": "))
+            .max_by_key(|s| s.name.len())
+            .map(|s| line.split_at(s.name.len()))
+            .map(|(name, rest)| (name, &rest[2..]))
+    }
+
+    // "p1 collected $0.13 from pot", without a colon after the name
+    fn parse_collected(&self, line: &str) -> Option<Collected> {
+        // "from pot", "from main pot", "from side pot" or "from side pot-1"
+        let side = line
+            .rfind(" side pot-")
+            .map(|p| &line[p + " side pot-".len()..]);
+        if !line.ends_with(" pot") && side.is_none_or(|n| n.parse::<u32>().is_err()) {
+            return None;
+        }
+        let (name, amount) = self
+            .seats
+            .iter()
+            .filter(|s| {
+                line.starts_with(&s.name) && line[s.name.len()..].starts_with(" collected ")
+            })
+            .max_by_key(|s| s.name.len())
+            .map(|s| (&s.name, &line[s.name.len() + " collected ".len()..]))?;
+        Some(Collected {
+            name: name.clone(),
+            amount: parse_chips(amount)?,
+        })
+    }
+
+    // chips won by name, all pots
+    pub fn collected(&self, name: &str) -> Chips {
+        self.collected
+            .iter()
+            .filter(|c| c.name == name)
+            .map(|c| c.amount)
+            .sum()
+    }
+
+    // chips won minus chips put in the pot, blinds and antes included
+    pub fn net(&self, name: &str) -> Chips {
+        let posted: Chips = self
+            .posts
+            .iter()
+            .filter(|p| p.name == name)
+            .map(|p| p.amount)
+            .sum();
+        let bet: Chips = self
+            .actions
+            .iter()
+            .filter(|a| a.name == name)
+            .map(|a| a.amount)
+            .sum();
+        let returned: Chips = self
+            .returned
+            .iter()
+            .filter(|r| r.name == name)
+            .map(|r| r.amount)
+            .sum();
+        self.collected(name) + returned - posted - bet
+    }
+
+    // names of players dealt in, in seat order
+    pub fn players(&self) -> Vec<String> {
+        self.seats.iter().map(|s| s.name.clone()).collect()
+    }
+
+    // name of the player seated at the button, empty if none
+    pub fn button_name(&self) -> &str {
+        self.seats
+            .iter()
+            .find(|s| s.seat == self.button)
+            .map_or("", |s| s.name.as_str())
+    }
+
+    // posted or acted, seated players sitting out do neither
+    pub fn is_dealt(&self, name: &str) -> bool {
+        self.posts.iter().any(|p| p.name == name) || self.actions.iter().any(|a| a.name == name)
+    }
+
+    // position of every player dealt in, starting with the first seat after the button.
+    // the blinds are who posted them, eg no SB when its seat is empty, the others are counted
+    // back from the button
+    pub fn positions(&self) -> Vec<(&str, Position)> {
+        use Position::*;
+        let sb = self.poster(PostKind::SmallBlind);
+        let bb = self.poster(PostKind::BigBlind);
+        let dealt: Vec<&Seat> = self
+            .seats
+            .iter()
+            .filter(|s| self.is_dealt(&s.name))
+            .collect();
+        // seats starting with the first one after the button
+        let first = dealt.iter().position(|s| s.seat > self.button).unwrap_or(0);
+        let seats = dealt[first..].iter().chain(&dealt[..first]);
+
+        let others = dealt
+            .iter()
+            .filter(|s| s.name != sb && s.name != bb)
+            .count();
+        let middle = others.saturating_sub(3); // between UTG and the cutoff
+        let mut positions = Vec::new();
+        if others >= 3 {
+            positions.extend(vec![Utg; 1 + middle / 2]);
+            positions.extend(vec![Mp; middle - middle / 2]);
+        }
+        if others >= 2 {
+            positions.push(Co);
+        }
+        if others >= 1 {
+            positions.push(Btn);
+        }
+        let mut positions = positions.into_iter();
+        seats
+            .filter_map(|s| {
+                let position = match s.name.as_str() {
+                    n if n == sb => Sb,
+                    n if n == bb => Bb,
+                    _ => positions.next()?,
+                };
+                Some((s.name.as_str(), position))
+            })
+            .collect()
+    }
+
+    pub fn position(&self, name: &str) -> Option<Position> {
+        self.positions()
+            .into_iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, p)| p)
+    }
+
+    // name of the player who posted the given blind, empty if none
+    pub fn poster(&self, kind: PostKind) -> &str {
+        self.posts
+            .iter()
+            .find(|p| p.kind == kind)
+            .map_or("", |p| p.name.as_str())
+    }
+
+    // names of players, in order of action, who did one of kinds on street
+    pub fn actors(&self, street: Street, kinds: &[ActionKind]) -> Vec<&str> {
+        self.actions
+            .iter()
+            .filter(|a| a.street == street && kinds.contains(&a.kind))
+            .map(|a| a.name.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a 3 handed hand, players in seats 1 to 3, button in seat 1
+    fn hand(names: [&str; 3], actions: &[&str]) -> String {
+        let mut text = String::from(
+            "PokerStars Hand #1:  Hold'em No Limit ($0.01/$0.02 USD) - 2020/05/30 10:11:12 ET\n\
+             Table 'Test' 6-max Seat #1 is the button\n",
+        );
+        for (i, name) in names.iter().enumerate() {
+            text += &format!("Seat {}: {} ($2 in chips)\n", i + 1, name);
+        }
+        text += &format!("{}: posts small blind $0.01\n", names[1]);
+        text += &format!("{}: posts big blind $0.02\n", names[2]);
+        text += "*** HOLE CARDS ***\n";
+        for action in actions {
+            text += action;
+            text += "\n";
+        }
+        text += "*** SUMMARY ***\n";
+        text
+    }
+
+    fn parsed(names: [&str; 3], actions: &[&str]) -> Vec<(String, ActionKind)> {
+        let hand = Hand::parse(&hand(names, actions)).unwrap();
+        assert_eq!(hand.players(), names);
+        hand.actions.into_iter().map(|a| (a.name, a.kind)).collect()
+    }
+
+    #[test]
+    fn names_with_colons() {
+        let names = ["bob", "bob: folds", "a:b"];
+        let actions = parsed(
+            names,
+            &[
+                "bob: folds: raises $0.04 to $0.06",
+                "a:b: calls $0.04",
+                "bob: folds",
+            ],
+        );
+        assert_eq!(
+            actions,
+            vec![
+                ("bob: folds".to_string(), ActionKind::Raise),
+                ("a:b".to_string(), ActionKind::Call),
+                ("bob".to_string(), ActionKind::Fold),
+            ]
+        );
+    }
+
+    #[test]
+    fn names_with_parentheses() {
+        let names = ["x (y)", "(z", "w)"];
+        let actions = parsed(
+            names,
+            &["x (y): raises $0.04 to $0.06", "(z: folds", "w): folds"],
+        );
+        assert_eq!(actions[0], ("x (y)".to_string(), ActionKind::Raise));
+        assert_eq!(actions[1], ("(z".to_string(), ActionKind::Fold));
+        assert_eq!(actions[2], ("w)".to_string(), ActionKind::Fold));
+    }
+
+    #[test]
+    fn names_with_spaces_and_unicode() {
+        let names = ["big fish", "Zoë ☃", "名人"];
+        let actions = parsed(
+            names,
+            &[
+                "big fish: calls $0.02",
+                "Zoë ☃: calls $0.01",
+                "名人: checks",
+            ],
+        );
+        assert_eq!(
+            actions,
+            vec![
+                ("big fish".to_string(), ActionKind::Call),
+                ("Zoë ☃".to_string(), ActionKind::Call),
+                ("名人".to_string(), ActionKind::Check),
+            ]
+        );
+    }
+
+    #[test]
+    fn names_with_action_keywords() {
+        let names = ["calls $1", "raises", "posts big blind"];
+        let hand = Hand::parse(&hand(
+            names,
+            &[
+                "calls $1: raises $0.04 to $0.06",
+                "raises: folds",
+                "posts big blind: calls $0.04",
+            ],
+        ))
+        .unwrap();
+        assert_eq!(hand.poster(PostKind::SmallBlind), "raises");
+        assert_eq!(hand.poster(PostKind::BigBlind), "posts big blind");
+        assert_eq!(hand.actions[0].name, "calls $1");
+        assert_eq!(hand.actions[0].to, 6);
+        assert_eq!(hand.actions[2].name, "posts big blind");
+        assert_eq!(hand.actions[2].kind, ActionKind::Call);
+    }
+
+    #[test]
+    fn seat_line_suffixes() {
+        let text = hand(["a", "b", "c"], &[]).replace(
+            "Seat 3: c ($2 in chips)",
+            "Seat 3: c (d) ($1.50 in chips) is sitting out",
+        );
+        let text = text.replace("c: posts", "c (d): posts");
+        let hand = Hand::parse(&text).unwrap();
+        assert_eq!(hand.seats[2].name, "c (d)");
+        assert_eq!(hand.seats[2].stack, 150);
+    }
+
+    #[test]
+    fn table_line_and_two_digit_seats() {
+        let text = hand(["a", "b", "c"], &[])
+            .replace(
+                "Table 'Test' 6-max Seat #1 is the button",
+                "Table 'Dan's #1' 10-max (Play Money) Seat #10 is the button",
+            )
+            .replace("Seat 1: a", "Seat 10: a");
+        let hand = Hand::parse(&text).unwrap();
+        assert_eq!(hand.table, "Dan's #1");
+        assert_eq!(hand.max_seats, 10);
+        assert_eq!(hand.money, Money::Play);
+        assert_eq!(hand.button, 10);
+        assert_eq!(hand.button_name(), "a");
+    }
+
+    #[test]
+    fn positions() {
+        use Position::*;
+        // seats taken, button seat, seats of the small and big blinds, 0 if none
+        let positions = |seats: &[u8], button: u8, blinds: (u8, u8)| -> Vec<Position> {
+            let post = |seat: u8, kind: PostKind| Post {
+                name: format!("p{}", seat),
+                kind,
+                amount: 0,
+                is_all_in: false,
+            };
+            let hand = Hand {
+                button,
+                seats: seats
+                    .iter()
+                    .map(|&i| Seat {
+                        seat: i,
+                        name: format!("p{}", i),
+                        stack: 200,
+                    })
+                    .collect(),
+                posts: [
+                    (blinds.0, PostKind::SmallBlind),
+                    (blinds.1, PostKind::BigBlind),
+                ]
+                .iter()
+                .filter(|(seat, _)| *seat > 0)
+                .map(|&(seat, kind)| post(seat, kind))
+                .collect(),
+                actions: seats
+                    .iter()
+                    .map(|&i| Action {
+                        street: Street::Preflop,
+                        name: format!("p{}", i),
+                        kind: ActionKind::Fold,
+                        amount: 0,
+                        to: 0,
+                        pot: 0,
+                        stack: 200,
+                        is_all_in: false,
+                    })
+                    .collect(),
+                ..Default::default()
+            };
+            seats
+                .iter()
+                .map(|i| hand.position(&format!("p{}", i)).unwrap())
+                .collect()
+        };
+        let seats: Vec<u8> = (1..=10).collect();
+        assert_eq!(positions(&seats[..2], 1, (1, 2)), [Sb, Bb]);
+        assert_eq!(positions(&seats[..3], 3, (1, 2)), [Sb, Bb, Btn]);
+        assert_eq!(positions(&seats[..4], 1, (2, 3)), [Btn, Sb, Bb, Co]);
+        assert_eq!(
+            positions(&seats[..6], 4, (5, 6)),
+            [Utg, Mp, Co, Btn, Sb, Bb]
+        );
+        assert_eq!(
+            positions(&seats[..9], 9, (1, 2)),
+            [Sb, Bb, Utg, Utg, Utg, Mp, Mp, Co, Btn]
+        );
+        assert_eq!(
+            positions(&seats, 5, (6, 7)),
+            [Mp, Mp, Mp, Co, Btn, Sb, Bb, Utg, Utg, Utg]
+        );
+        // seat 5 is empty, the big blind is seat 6 and there's no small blind
+        assert_eq!(
+            positions(&[1, 2, 3, 4, 6], 4, (0, 6)),
+            [Utg, Mp, Co, Btn, Bb]
+        );
+    }
+
+    #[test]
+    fn positions_skip_sitting_out_seats() {
+        use Position::*;
+        let text =
+            "PokerStars Hand #1:  Hold'em No Limit ($0.01/$0.02 USD) - 2020/05/30 10:11:12 ET\n\
+             Table 'Test' 6-max Seat #1 is the button\n\
+             Seat 1: p1 ($2 in chips)\nSeat 2: p2 ($2 in chips) is sitting out\n\
+             Seat 3: p3 ($2 in chips)\nSeat 4: p4 ($2 in chips)\nSeat 5: p5 ($2 in chips)\n\
+             p3: posts small blind $0.01\np4: posts big blind $0.02\n*** HOLE CARDS ***\n\
+             p5: folds\np1: folds\np3: folds\n*** SUMMARY ***\n";
+        let hand = Hand::parse(text).unwrap();
+        assert!(!hand.is_dealt("p2"));
+        assert_eq!(
+            hand.positions(),
+            [("p3", Sb), ("p4", Bb), ("p5", Co), ("p1", Btn)]
+        );
+        assert_eq!(hand.position("p2"), None);
+    }
+
+    #[test]
+    fn header_date() {
+        let text = hand(["a", "b", "c"], &[]);
+        assert_eq!(Hand::parse(&text).unwrap().date, "2020-05-30 10:11:12");
+        let text = hand(["a", "b", "c"], &[]).replace(
+            "2020/05/30 10:11:12 ET",
+            "2020/05/30 10:11:12 CET [2020/05/30 4:11:12 ET]",
+        );
+        assert_eq!(Hand::parse(&text).unwrap().date, "2020-05-30 04:11:12");
+    }
+
+    #[test]
+    fn board_showdown_and_pots() {
+        let showdown = Hand::parse(&hand(
+            ["a", "b", "b collected"],
+            &[
+                "a: raises $0.04 to $0.06",
+                "b: calls $0.05",
+                "b collected: calls $0.04",
+                "*** FLOP *** [2c 7h Td]",
+                "b: bets $1.94 and is all-in",
+                "b collected: calls $1.94 and is all-in",
+                "a: folds",
+                "*** TURN *** [2c 7h Td] [Js]",
+                "*** RIVER *** [2c 7h Td Js] [3d]",
+                "*** SHOW DOWN ***",
+                "b: shows [Ah Ad] (a pair of Aces)",
+                "b collected: mucks hand",
+                "b collected collected $4 from side pot-1",
+                "b collected collected $0.06 from main pot",
+                "b: doesn't show hand",
+            ],
+        ))
+        .unwrap();
+        assert_eq!(showdown.board, ["2c", "7h", "Td", "Js", "3d"]);
+        assert_eq!(showdown.showdown, ["b", "b collected"]);
+        assert_eq!(showdown.collected("b collected"), 406);
+        assert_eq!((showdown.collected("b"), showdown.collected.len()), (0, 2));
+
+        // shown without a showdown
+        let folded = Hand::parse(&hand(
+            ["a", "b", "c"],
+            &[
+                "a: raises $0.04 to $0.06",
+                "b: folds",
+                "c: folds",
+                "Uncalled bet ($0.04) returned to a",
+                "a collected $0.05 from pot",
+                "a: shows [Ah Ad] (a pair of Aces)",
+            ],
+        ))
+        .unwrap();
+        assert!(folded.board.is_empty() && folded.showdown.is_empty());
+        assert_eq!(folded.collected("a"), 5);
+    }
+
+    #[test]
+    fn net_rake_and_jackpot() {
+        let text = hand(
+            ["a", "b", "c"],
+            &[
+                "a: raises $0.04 to $0.06",
+                "b: raises $0.18 to $0.24",
+                "c: folds",
+                "a: calls $0.18",
+                "*** FLOP *** [2c 7h Td]",
+                "b: bets $0.50",
+                "a: folds",
+                "Uncalled bet ($0.50) returned to b",
+                "b collected $0.47 from pot",
+            ],
+        ) + "Total pot $0.50 | Rake $0.02 | Jackpot $0.01 | Bingo $0\n\
+             Board [2c 7h Td]\n\
+             Seat 2: b (small blind) collected ($0.47)\n";
+        let hand = Hand::parse(&text).unwrap();
+        assert_eq!((hand.pot, hand.rake, hand.jackpot), (50, 2, 1));
+        assert_eq!(hand.net("a"), -24);
+        assert_eq!(hand.net("b"), 23);
+        assert_eq!(hand.net("c"), -2);
+        assert_eq!(hand.collected.len(), 1); // not the summary line
+        let total: Chips = hand.players().iter().map(|p| hand.net(p)).sum();
+        assert_eq!(total, -(hand.rake + hand.jackpot));
+
+        let side_pots = text.replace(
+            "Total pot $0.50 | Rake $0.02 | Jackpot $0.01 | Bingo $0",
+            "Total pot $10 Main pot $6. Side pot $3.50. | Rake $0.50",
+        );
+        let hand = Hand::parse(&side_pots).unwrap();
+        assert_eq!((hand.pot, hand.rake, hand.jackpot), (1000, 50, 0));
+    }
+
+    #[test]
+    fn action_amounts_pot_and_stacks() {
+        let hand = Hand::parse(&hand(
+            ["a", "b", "c"],
+            &[
+                "a: raises $0.04 to $0.06",
+                "b: raises $0.14 to $0.20",
+                "c: calls $0.18",
+                "a: folds",
+                "*** FLOP *** [2c 7h Td]",
+                "b: bets $1.80 and is all-in",
+                "c: folds",
+                "Uncalled bet ($1.80) returned to b",
+                "b collected $0.46 from pot",
+            ],
+        ))
+        .unwrap();
+        let actions: Vec<_> = hand
+            .actions
+            .iter()
+            .map(|a| (a.name.as_str(), a.amount, a.to, a.pot, a.stack, a.is_all_in))
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                ("a", 6, 6, 3, 194, false),
+                ("b", 19, 20, 9, 180, false), // the small blind is already in
+                ("c", 18, 0, 28, 180, false),
+                ("a", 0, 0, 46, 194, false),
+                ("b", 180, 180, 46, 0, true),
+                ("c", 0, 0, 226, 180, false),
+            ]
+        );
+        assert_eq!(hand.returned.len(), 1);
+        assert_eq!(
+            (hand.returned[0].name.as_str(), hand.returned[0].amount),
+            ("b", 180)
+        );
+        assert_eq!((hand.net("a"), hand.net("b"), hand.net("c")), (-6, 26, -20));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let text = hand(["a", "b", "c"], &["a: folds", "b: folds"]);
+        let err = |from: &str, to: &str| Hand::parse(&text.replacen(from, to, 1)).unwrap_err();
+
+        let cut = &text[..text.find("*** HOLE CARDS ***").unwrap()];
+        assert_eq!(
+            Hand::parse(cut).unwrap_err(),
+            ParseError::Truncated { line: 7 }
+        );
+        let cut = &text[..text.find("*** SUMMARY ***").unwrap()];
+        assert_eq!(
+            Hand::parse(cut).unwrap_err(),
+            ParseError::Truncated { line: 10 }
+        );
+
+        assert_eq!(
+            err("PokerStars Hand #1:", "PokerStars Game #1:"),
+            ParseError::MissingHeader { line: 1 }
+        );
+        assert_eq!(
+            err("Table 'Test' 6-max Seat #1 is the button\n", ""),
+            ParseError::MissingHeader { line: 7 }
+        );
+        assert_eq!(
+            err("Seat 2: b ($2 in chips)", "Seat 2: b $2 in chips"),
+            ParseError::BadSeatLine {
+                line: 4,
+                text: "Seat 2: b $2 in chips".to_string()
+            }
+        );
+        assert_eq!(
+            err("b: folds", "b: dances"),
+            ParseError::UnknownAction {
+                line: 10,
+                text: "b: dances".to_string()
+            }
+        );
+    }
+}
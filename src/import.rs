@@ -0,0 +1,503 @@
+// bulk import of old hand histories: files, directories and zip archives
+
+use crate::config::HistoryDir;
+use crate::db::Database;
+use crate::files;
+use crate::hand::Hand;
+use crate::stats::{Stats, K_STATS_VERSION};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::iter;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+#[derive(Default, Debug)]
+pub struct Summary {
+    pub files: usize,      // hand history files, archive members included
+    pub hands: usize,      // hands added to the database
+    pub duplicates: usize, // hands already in the database
+    pub errors: usize,     // hands and files that could not be read
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} files, {} new hands, {} already imported, {} errors, {:.1} sec",
+            self.files,
+            self.hands,
+            self.duplicates,
+            self.errors,
+            self.elapsed.as_secs_f64()
+        )
+    }
+}
+
+// a hand history file: name for error messages, and contents
+type Source = (String, Result<String, String>);
+
+// hands sent to workers at once, few enough to spread the last file over all of them
+const K_BATCH_HANDS: usize = 64;
+
+// hands of a file, as ranges of its contents
+struct Batch {
+    name: Arc<str>,
+    contents: Arc<str>,
+    hands: Vec<Range<usize>>,
+}
+
+// what a worker did, its stats are merged into the database at the end
+#[derive(Default)]
+struct Partial {
+    stats: Stats,
+    hands: usize,
+    duplicates: usize,
+    errors: usize,
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case(extension))
+}
+
+// files named on the command line, and hand histories and archives found in directories,
+// and in their subdirectories if recursive
+fn sources<'a>(paths: impl IntoIterator<Item = (&'a Path, bool)>) -> Vec<PathBuf> {
+    let mut sources = Vec::new();
+    for (path, recursive) in paths {
+        if path.is_dir() {
+            sources.extend(
+                files::list(path, recursive)
+                    .into_iter()
+                    .filter(|p| has_extension(p, "txt") || has_extension(p, "zip")),
+            );
+        } else {
+            sources.push(path.to_path_buf());
+        }
+    }
+    sources
+}
+
+// directories of the command line are read recursively
+fn command_line(paths: &[PathBuf]) -> Vec<PathBuf> {
+    sources(paths.iter().map(|p| (p.as_path(), true)))
+}
+
+// contents of a file, or of every file of an archive, read as they are needed
+fn read(path: PathBuf) -> Box<dyn Iterator<Item = Source> + Send> {
+    let name = path.display().to_string();
+    if !has_extension(&path, "zip") {
+        let contents = fs::read(&path)
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            .map_err(|e| e.to_string());
+        return Box::new(iter::once((name, contents)));
+    }
+    let mut archive = match fs::File::open(&path)
+        .map_err(ZipError::from)
+        .and_then(ZipArchive::new)
+    {
+        Ok(archive) => archive,
+        Err(e) => return Box::new(iter::once((name, Err(e.to_string())))),
+    };
+    Box::new((0..archive.len()).filter_map(move |i| {
+        let mut member = match archive.by_index(i) {
+            Ok(member) => member,
+            Err(e) => return Some((name.clone(), Err(e.to_string()))),
+        };
+        if !member.is_file() {
+            return None;
+        }
+        let name = format!("{}/{}", name, member.name());
+        let mut bytes = Vec::new();
+        let contents = member
+            .read_to_end(&mut bytes)
+            .map(|_| String::from_utf8_lossy(&bytes).into_owned())
+            .map_err(|e| e.to_string());
+        Some((name, contents))
+    }))
+}
+
+pub fn import(db: &mut Database, paths: &[PathBuf]) -> Summary {
+    pipeline(db, command_line(paths).into_iter().flat_map(read))
+}
+
+// counts stats again from the hands of paths only, eg after a stat definition changed
+pub fn rebuild(db: &mut Database, paths: &[PathBuf]) -> Summary {
+    rebuild_sources(db, command_line(paths))
+}
+
+// same from the history directories, with their own recursive setting
+pub fn rebuild_from_dirs(db: &mut Database, dirs: &[HistoryDir]) -> Summary {
+    rebuild_sources(
+        db,
+        sources(dirs.iter().map(|d| (d.path.as_path(), d.recursive))),
+    )
+}
+
+fn rebuild_sources(db: &mut Database, sources: Vec<PathBuf>) -> Summary {
+    db.stats = Stats::default();
+    db.hands.clear();
+    // players shown in the hud may not be in the new stats, the next hand of each table sets them
+    db.files.0.values_mut().for_each(|f| f.players.clear());
+    let summary = pipeline(db, sources.into_iter().flat_map(read));
+    db.stats_version = K_STATS_VERSION;
+    summary
+}
+
+// same from the hands of the sqlite store
+pub fn rebuild_from_store(db: &mut Database) -> Result<Summary, String> {
+    let start = Instant::now();
+    let store = db.store.as_ref().ok_or("no sqlite store")?;
+    // counting them would silently miss their showdowns and pots
+    let outdated = store.outdated_hands()?;
+    if outdated > 0 {
+        return Err(format!(
+            "{} hands were stored by an older version without all their data, \
+             rebuild from the hand history files with rpokerhud rebuild PATH..",
+            outdated
+        ));
+    }
+    let mut stats = Stats::default();
+    let mut hands = HashSet::new();
+    store.for_each_hand(|hand| {
+        hands.insert(hand.id);
+        stats.populate(&hand);
+    })?;
+    let summary = Summary {
+        hands: hands.len(),
+        elapsed: start.elapsed(),
+        ..Default::default()
+    };
+    db.stats = stats;
+    db.hands = hands;
+    db.files.0.values_mut().for_each(|f| f.players.clear());
+    db.stats_version = K_STATS_VERSION;
+    Ok(summary)
+}
+
+// one thread reads files and splits them into hands, a pool of workers parses the hands
+// and counts them in their own stats, which are added to the database at the end
+fn pipeline(db: &mut Database, sources: impl Iterator<Item = Source> + Send) -> Summary {
+    let start = Instant::now();
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    let (tx, rx) = mpsc::sync_channel::<Batch>(4 * workers); // bounded, the splitter waits for the workers
+    let rx = Mutex::new(rx);
+    let ids = &Mutex::new(&mut db.hands); // hands counted, by any worker
+    let rx = &rx;
+
+    // new hands also go to the sqlite store, from this thread as its connection can't be shared
+    let mut store = db.store.as_mut();
+    let (stored_tx, stored_rx) = mpsc::sync_channel::<Hand>(K_BATCH_HANDS * workers);
+    let stored_tx = store.is_some().then_some(stored_tx);
+
+    let mut summary = Summary::default();
+    let partials = thread::scope(|s| {
+        let splitter = s.spawn(move || {
+            let (mut files, mut errors) = (0, 0);
+            for (name, contents) in sources {
+                files += 1;
+                let contents: Arc<str> = match contents {
+                    Ok(contents) => contents.into(),
+                    Err(e) => {
+                        eprintln!("{}: {}", name, e);
+                        errors += 1;
+                        continue;
+                    }
+                };
+                let name: Arc<str> = name.into();
+                let hands: Vec<Range<usize>> = files::split_hands(&contents)
+                    .iter()
+                    .map(|text| {
+                        let start = text.as_ptr() as usize - contents.as_ptr() as usize;
+                        start..start + text.len()
+                    })
+                    .collect();
+                for hands in hands.chunks(K_BATCH_HANDS) {
+                    let batch = Batch {
+                        name: name.clone(),
+                        contents: contents.clone(),
+                        hands: hands.to_vec(),
+                    };
+                    if tx.send(batch).is_err() {
+                        return (files, errors); // workers are gone
+                    }
+                }
+            }
+            (files, errors)
+        });
+
+        let workers: Vec<_> = (0..workers)
+            .map(|_| {
+                let stored_tx = stored_tx.clone();
+                s.spawn(move || {
+                    let mut partial = Partial::default();
+                    loop {
+                        // lock only held while waiting for a batch, not while parsing it
+                        let batch = rx.lock().unwrap().recv();
+                        let batch = match batch {
+                            Ok(batch) => batch,
+                            Err(_) => break, // splitter is done
+                        };
+                        for range in batch.hands {
+                            let hand = match Hand::parse(&batch.contents[range]) {
+                                Ok(hand) => hand,
+                                Err(e) => {
+                                    eprintln!("{}: skipping hand, {}", batch.name, e);
+                                    partial.errors += 1;
+                                    continue;
+                                }
+                            };
+                            if ids.lock().unwrap().insert(hand.id) {
+                                partial.stats.populate(&hand);
+                                partial.hands += 1;
+                                if let Some(stored_tx) = &stored_tx {
+                                    let _ = stored_tx.send(hand); // this thread is there until the end
+                                }
+                            } else {
+                                partial.duplicates += 1;
+                            }
+                        }
+                    }
+                    partial
+                })
+            })
+            .collect();
+
+        drop(stored_tx); // stored_rx ends with the workers
+        if let Some(store) = store.as_mut() {
+            if let Err(e) = store.insert(stored_rx.iter()) {
+                eprintln!("{}", e);
+            }
+        }
+        stored_rx.iter().for_each(drop); // after an error, workers still send their hands
+
+        (summary.files, summary.errors) = splitter.join().unwrap();
+        workers
+            .into_iter()
+            .map(|w| w.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    for partial in partials {
+        db.stats.merge(partial.stats);
+        summary.hands += partial.hands;
+        summary.duplicates += partial.duplicates;
+        summary.errors += partial.errors;
+    }
+    summary.elapsed = start.elapsed();
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    // synthetic 6-max hands, players taken from a pool so there are many of them
+    fn corpus(files: usize, hands_per_file: usize) -> impl Iterator<Item = Source> + Send {
+        const K_POOL: u64 = 5000;
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut random = move |n: u64| {
+            // xorshift
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed % n
+        };
+        (0..files).map(move |f| {
+            let mut contents = String::new();
+            for h in 0..hands_per_file {
+                let first = random(K_POOL);
+                let p: Vec<String> = (0..6)
+                    .map(|i| format!("player{}", (first + i) % K_POOL))
+                    .collect();
+                let (opener, caller) = (random(4) as usize, 4 + random(2) as usize);
+                contents += &format!(
+                    "PokerStars Hand #{}:  Hold'em No Limit ($0.01/$0.02 USD) - 2020/05/30 10:11:12 ET\r\n\
+                     Table 'Bench {}' 6-max Seat #{} is the button\r\n",
+                    f * hands_per_file + h + 1,
+                    f,
+                    opener + 1
+                );
+                for (i, name) in p.iter().enumerate() {
+                    contents += &format!("Seat {}: {} ($2 in chips)\r\n", i + 1, name);
+                }
+                contents += &format!(
+                    "{}: posts small blind $0.01\r\n{}: posts big blind $0.02\r\n*** HOLE CARDS ***\r\n",
+                    p[4], p[5]
+                );
+                for (i, name) in p.iter().enumerate().take(4) {
+                    match i == opener {
+                        true => contents += &format!("{}: raises $0.04 to $0.06\r\n", name),
+                        false => contents += &format!("{}: folds\r\n", name),
+                    }
+                }
+                contents += &format!("{}: calls $0.05\r\n", p[caller]);
+                if caller == 4 {
+                    contents += &format!("{}: folds\r\n", p[5]);
+                }
+                contents += &format!(
+                    "*** FLOP *** [2c 7h Td]\r\n{}: checks\r\n{}: bets $0.10\r\n{}: folds\r\n\
+                     Uncalled bet ($0.10) returned to {}\r\n{} collected $0.13 from pot\r\n\
+                     *** SUMMARY ***\r\nTotal pot $0.13 | Rake $0\r\n\r\n\r\n\r\n",
+                    p[caller], p[opener], p[caller], p[opener], p[opener]
+                );
+            }
+            (format!("bench{}.txt", f), Ok(contents))
+        })
+    }
+
+    fn assert_same_stats(stats: &Stats, expected: &Stats) {
+        assert_eq!(stats.0.len(), expected.0.len());
+        for (name, stat) in &expected.0 {
+            assert_eq!(format!("{:?}", stats.0[name]), format!("{:?}", stat));
+        }
+    }
+
+    #[test]
+    fn pipeline_counts_every_hand_once() {
+        let mut db = Database::default();
+        let summary = pipeline(&mut db, corpus(20, 50));
+        assert_eq!(
+            (summary.files, summary.hands, summary.errors),
+            (20, 1000, 0)
+        );
+
+        // same stats as adding the hands one by one
+        let mut expected = Database::default();
+        for (_, contents) in corpus(20, 50) {
+            for text in files::split_hands(&contents.unwrap()) {
+                expected.populate(&Hand::parse(text).unwrap());
+            }
+        }
+        assert_same_stats(&db.stats, &expected.stats);
+
+        let summary = pipeline(&mut db, corpus(20, 50));
+        assert_eq!((summary.hands, summary.duplicates), (0, 1000));
+    }
+
+    #[test]
+    fn import_walks_directories_and_archives() {
+        use std::io::Write;
+        let temp = TempDir::new("import");
+        let dir = temp.path().to_path_buf();
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let mut files = corpus(3, 2).map(|(_, contents)| contents.unwrap());
+        fs::write(dir.join("a.txt"), files.next().unwrap()).unwrap();
+        let mut zip = zip::ZipWriter::new(fs::File::create(dir.join("sub").join("b.zip")).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.add_directory("old/", options).unwrap();
+        for (i, contents) in files.enumerate() {
+            zip.start_file(format!("old/{}.txt", i), options).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        fs::write(dir.join("bad.zip"), "not an archive").unwrap();
+        fs::write(dir.join("notes.md"), "not a hand history").unwrap();
+
+        let mut db = Database::default();
+        let summary = import(&mut db, std::slice::from_ref(&dir));
+        // a.txt, the two files of b.zip and bad.zip
+        assert_eq!(
+            (
+                summary.files,
+                summary.hands,
+                summary.duplicates,
+                summary.errors
+            ),
+            (4, 6, 0, 1)
+        );
+        let summary = import(&mut db, &[dir.join("sub").join("b.zip"), dir.join("a.txt")]);
+        assert_eq!(
+            (
+                summary.files,
+                summary.hands,
+                summary.duplicates,
+                summary.errors
+            ),
+            (3, 0, 6, 0)
+        );
+    }
+
+    #[test]
+    fn rebuild_counts_again_from_paths_or_dirs() {
+        let temp = TempDir::new("rebuild");
+        let dir = temp.path().to_path_buf();
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let mut files = corpus(2, 3).map(|(_, contents)| contents.unwrap());
+        fs::write(dir.join("a.txt"), files.next().unwrap()).unwrap();
+        fs::write(dir.join("sub").join("b.txt"), files.next().unwrap()).unwrap();
+        let mut expected = Database::default();
+        import(&mut expected, std::slice::from_ref(&dir));
+
+        // counted with older definitions, and a player shown in the hud not counted again
+        let mut db = Database::default();
+        import(&mut db, &[dir.join("a.txt")]);
+        db.stats_version = K_STATS_VERSION - 1;
+        db.stats.0.entry("ghost".to_string()).or_default().handsNo = 9;
+        db.files.0.entry("t.txt".to_string()).or_default().players = vec!["ghost".to_string()];
+        assert!(db.is_stale());
+
+        let summary = rebuild(&mut db, std::slice::from_ref(&dir));
+        assert_eq!((summary.hands, summary.duplicates), (6, 0));
+        assert!(!db.is_stale());
+        assert_same_stats(&db.stats, &expected.stats);
+        assert!(db.files.0["t.txt"].players.is_empty());
+
+        // subdirectories only if the history dir is recursive
+        let mut history_dir = HistoryDir {
+            path: dir.clone(),
+            ..Default::default()
+        };
+        let summary = rebuild_from_dirs(&mut db, std::slice::from_ref(&history_dir));
+        assert_eq!((summary.files, summary.hands, db.hands.len()), (1, 3, 3));
+        history_dir.recursive = true;
+        let summary = rebuild_from_dirs(&mut db, &[history_dir]);
+        assert_eq!((summary.files, summary.hands), (2, 6));
+        assert_same_stats(&db.stats, &expected.stats);
+
+        assert!(rebuild_from_store(&mut db).is_err()); // no store
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn rebuild_from_store_counts_stored_hands() {
+        let mut db = Database {
+            store: Some(crate::store::Store::open(Path::new(":memory:")).unwrap()),
+            ..Default::default()
+        };
+        pipeline(&mut db, corpus(2, 3));
+        let mut expected = Database::default();
+        pipeline(&mut expected, corpus(2, 3));
+
+        db.stats = Stats::default();
+        db.stats_version = K_STATS_VERSION - 1;
+        let summary = rebuild_from_store(&mut db).unwrap();
+        assert_eq!(summary.hands, 6);
+        assert!(!db.is_stale());
+        assert_same_stats(&db.stats, &expected.stats);
+    }
+
+    // cargo test --release bench -- --ignored --nocapture
+    // hands are generated by the splitter thread, in place of reading them from disk
+    #[test]
+    #[ignore]
+    fn bench_1m_hands() {
+        let mut db = Database::default();
+        let summary = pipeline(&mut db, corpus(1000, 1000));
+        assert_eq!(summary.hands, 1_000_000);
+        println!(
+            "{}, {:.0} hands/sec",
+            summary,
+            summary.hands as f64 / summary.elapsed.as_secs_f64()
+        );
+    }
+}
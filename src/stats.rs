@@ -0,0 +1,1251 @@
+// player stats, derived from parsed hands
+
+use crate::hand::{ActionKind, Chips, Hand, Money, Position, PostKind, Street};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)] // fields missing from older databases start at 0
+pub struct Stat {
+    pub handsNo: u32,
+    pub vpip: u32,
+    pub pfr: u32,
+    pub threeBet: u32,
+    pub threeBetCould: u32,
+    pub foldThreeBet: u32, // so F3B stat = number of F3B/situation where could F3B
+    pub foldThreeBetCould: u32,
+    pub callThreeBet: u32, // could = foldThreeBetCould
+    pub fourBet: u32,
+    pub fourBetCould: u32,
+    pub foldFourBet: u32,
+    pub foldFourBetCould: u32,
+    pub callFourBet: u32, // could = foldFourBetCould
+    pub fiveBet: u32,
+    pub fiveBetCould: u32,
+    pub steal: u32,
+    pub stealCould: u32,
+    pub foldSteal: u32,
+    pub foldStealCould: u32,
+    pub cbet: u32,
+    pub cbetCould: u32,
+    pub foldCbet: u32,
+    pub foldCbetCould: u32,
+    pub craise: u32, // flop check-raise, could = checked then faced a bet
+    pub craiseCould: u32,
+    pub donk: u32, // flop bet into the preflop aggressor, who is still to act
+    pub donkCould: u32,
+    pub turnCraise: u32,
+    pub turnCraiseCould: u32,
+    pub riverCraise: u32,
+    pub riverCraiseCould: u32,
+    pub turnDonk: u32, // into the flop aggressor
+    pub turnDonkCould: u32,
+    pub riverDonk: u32,
+    pub riverDonkCould: u32,
+    pub cbetSize: u32,  // sum of flop c-bets in % of pot, average = cbetSize / cbet
+    pub raiseCbet: u32, // could = foldCbetCould
+    pub turnCbet: u32,  // second barrel, after an unraised flop c-bet
+    pub turnCbetCould: u32,
+    pub foldTurnCbet: u32,
+    pub foldTurnCbetCould: u32,
+    pub riverCbet: u32, // third barrel
+    pub riverCbetCould: u32,
+    pub foldRiverCbet: u32,
+    pub foldRiverCbetCould: u32,
+    pub delayedCbet: u32, // turn bet after checking the flop through
+    pub delayedCbetCould: u32,
+    pub openRaise: u32,     // first raise preflop, no limpers
+    pub openRaiseSize: u32, // sum of open raises in hundredths of bb, average = openRaiseSize / openRaise
+    pub raiseFirstIn: u32,  // open raise with only folds before, not from the big blind
+    pub raiseFirstInCould: u32,
+    pub limp: u32, // call the big blind preflop, other limpers or not
+    pub limpCould: u32,
+    pub limpCall: u32, // limp then call a raise
+    pub limpCallCould: u32,
+    pub limpRaise: u32, // could = limpCallCould
+    pub coldCall: u32,  // call a raise with no money in the pot but blinds
+    pub coldCallCould: u32,
+    pub squeeze: u32, // 3-bet an open raise that was called
+    pub squeezeCould: u32,
+    pub isoRaise: u32, // raise limpers
+    pub isoRaiseCould: u32,
+    pub flopAggression: u32, // bets and raises, AF = aggression / calls, AFq = aggression / (aggression + calls + folds)
+    pub flopCall: u32,
+    pub flopFold: u32,
+    pub turnAggression: u32,
+    pub turnCall: u32,
+    pub turnFold: u32,
+    pub riverAggression: u32,
+    pub riverCall: u32,
+    pub riverFold: u32,
+    pub sawFlop: u32,
+    pub showdown: u32, // went to showdown having seen the flop, WTSD = showdown / sawFlop
+    pub showdownWon: u32, // won chips at showdown, W$SD = showdownWon / showdown
+    pub sawFlopWon: u32, // won chips having seen the flop, WWSF = sawFlopWon / sawFlop
+    pub net: i64,      // chips won minus chips put in, in hundredths, real money games only
+    pub rake: i64,     // share of the rake of the pots won, proportional to the chips won, same
+    pub netBb: i64, // net in hundredths of the big blind of each hand, all games. bb/100 = netBb / handsNo
+    // the same counters for hands played from each position, in Position::ALL order.
+    // empty for those of a position, and for players not seen since they were added
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub byPosition: Vec<Stat>,
+}
+
+#[derive(Default, Serialize, Deserialize, Debug)]
+pub struct Stats(pub HashMap<String, Stat>); // keys are player names
+
+// version of the counter definitions, to bump when one of them changes so that databases
+// counted with the previous ones are flagged until rebuilt (rpokerhud rebuild)
+// 2: preflop raise levels, 3-bet opportunities for every player facing an open raise
+// 3: counters by position
+// 4: c-bets by the last preflop raiser when checked to, folds to c-bet by anyone facing one
+// 5: check-raises and donk bets only when the player faced that decision
+// 6: net won, rake and bb/100 counted on every hand, not only those since they were added
+//    players sitting out not counted, blinds by who posted them
+pub const K_STATS_VERSION: u32 = 6;
+
+// hud columns available, the default ones are in config.rs
+// any of them can also be shown for one position, eg vpi:BTN or RFI:SB
+// AF and AFq are postflop, FAF, TAF, RAF etc for the flop, turn and river only
+// Net and Rake are in dollars, bb100 in big blinds per 100 hands
+// c-bets: CB, TCB and RCB on the flop, turn and river, FCB etc to fold to them, RaCB to raise
+// the flop one, DCB delayed. CR and Dk check-raise and donk on the flop, TCR, RDk etc later
+pub const K_COLUMNS: [&str; 49] = [
+    "vpi", "pfr", "3B", "No", "F3B", "C3B", "4B", "F4B", "C4B", "5B", "ST", "FS", "CB", "FCB",
+    "CR", "Dk", "CBs", "OR", "RFI", "LMP", "LC", "LR", "CC", "SQZ", "ISO", "AF", "AFq", "FAF",
+    "FAFq", "TAF", "TAFq", "RAF", "RAFq", "WTSD", "W$SD", "WWSF", "Net", "Rake", "bb100", "RaCB",
+    "TCB", "FTCB", "RCB", "FRCB", "DCB", "TCR", "RCR", "TDk", "RDk",
+];
+
+impl Stat {
+    // value shown in a hud column, None if there's no such column
+    pub fn column(&self, column: &str) -> Option<String> {
+        // percentage of times done when could do it
+        let pct = |done: u32, could: u32| (100 * done / std::cmp::max(could, 1)).to_string();
+        // aggression factor and frequency
+        let af = |aggression: u32, calls: u32| {
+            format!("{:.1}", aggression as f32 / std::cmp::max(calls, 1) as f32)
+        };
+        let afq =
+            |aggression: u32, calls: u32, folds: u32| pct(aggression, aggression + calls + folds);
+        let aggression = self.flopAggression + self.turnAggression + self.riverAggression;
+        let calls = self.flopCall + self.turnCall + self.riverCall;
+        let folds = self.flopFold + self.turnFold + self.riverFold;
+        if let Some((column, position)) = column.split_once(':') {
+            let i = Position::ALL.iter().position(|p| p.name() == position)?;
+            return match self.byPosition.get(i) {
+                Some(stat) => stat.column(column),
+                None => Stat::default().column(column),
+            };
+        }
+        Some(match column {
+            "vpi" => pct(self.vpip, self.handsNo),
+            "pfr" => pct(self.pfr, self.handsNo),
+            "3B" => pct(self.threeBet, self.threeBetCould),
+            "No" => self.handsNo.to_string(),
+            "F3B" => pct(self.foldThreeBet, self.foldThreeBetCould),
+            "C3B" => pct(self.callThreeBet, self.foldThreeBetCould),
+            "4B" => pct(self.fourBet, self.fourBetCould),
+            "F4B" => pct(self.foldFourBet, self.foldFourBetCould),
+            "C4B" => pct(self.callFourBet, self.foldFourBetCould),
+            "5B" => pct(self.fiveBet, self.fiveBetCould),
+            "ST" => pct(self.steal, self.stealCould),
+            "FS" => pct(self.foldSteal, self.foldStealCould),
+            "CB" => pct(self.cbet, self.cbetCould),
+            "FCB" => pct(self.foldCbet, self.foldCbetCould),
+            "CR" => pct(self.craise, self.craiseCould),
+            "Dk" => pct(self.donk, self.donkCould),
+            "TCR" => pct(self.turnCraise, self.turnCraiseCould),
+            "RCR" => pct(self.riverCraise, self.riverCraiseCould),
+            "TDk" => pct(self.turnDonk, self.turnDonkCould),
+            "RDk" => pct(self.riverDonk, self.riverDonkCould),
+            "CBs" => (self.cbetSize / std::cmp::max(self.cbet, 1)).to_string(),
+            "RaCB" => pct(self.raiseCbet, self.foldCbetCould),
+            "TCB" => pct(self.turnCbet, self.turnCbetCould),
+            "FTCB" => pct(self.foldTurnCbet, self.foldTurnCbetCould),
+            "RCB" => pct(self.riverCbet, self.riverCbetCould),
+            "FRCB" => pct(self.foldRiverCbet, self.foldRiverCbetCould),
+            "DCB" => pct(self.delayedCbet, self.delayedCbetCould),
+            "OR" => format!(
+                "{:.1}",
+                self.openRaiseSize as f32 / 100.0 / std::cmp::max(self.openRaise, 1) as f32
+            ),
+            "RFI" => pct(self.raiseFirstIn, self.raiseFirstInCould),
+            "LMP" => pct(self.limp, self.limpCould),
+            "LC" => pct(self.limpCall, self.limpCallCould),
+            "LR" => pct(self.limpRaise, self.limpCallCould),
+            "CC" => pct(self.coldCall, self.coldCallCould),
+            "SQZ" => pct(self.squeeze, self.squeezeCould),
+            "ISO" => pct(self.isoRaise, self.isoRaiseCould),
+            "AF" => af(aggression, calls),
+            "AFq" => afq(aggression, calls, folds),
+            "FAF" => af(self.flopAggression, self.flopCall),
+            "FAFq" => afq(self.flopAggression, self.flopCall, self.flopFold),
+            "TAF" => af(self.turnAggression, self.turnCall),
+            "TAFq" => afq(self.turnAggression, self.turnCall, self.turnFold),
+            "RAF" => af(self.riverAggression, self.riverCall),
+            "RAFq" => afq(self.riverAggression, self.riverCall, self.riverFold),
+            "WTSD" => pct(self.showdown, self.sawFlop),
+            "W$SD" => pct(self.showdownWon, self.showdown),
+            "WWSF" => pct(self.sawFlopWon, self.sawFlop),
+            "Net" => format!("{:.2}", self.net as f64 / 100.0),
+            "Rake" => format!("{:.2}", self.rake as f64 / 100.0),
+            "bb100" => format!(
+                "{:.1}",
+                self.netBb as f64 / std::cmp::max(self.handsNo, 1) as f64
+            ),
+            _ => return None,
+        })
+    }
+
+    // add counters of other to self
+    pub fn add(&mut self, other: &Stat) {
+        self.handsNo += other.handsNo;
+        self.vpip += other.vpip;
+        self.pfr += other.pfr;
+        self.threeBet += other.threeBet;
+        self.threeBetCould += other.threeBetCould;
+        self.foldThreeBet += other.foldThreeBet;
+        self.foldThreeBetCould += other.foldThreeBetCould;
+        self.callThreeBet += other.callThreeBet;
+        self.fourBet += other.fourBet;
+        self.fourBetCould += other.fourBetCould;
+        self.foldFourBet += other.foldFourBet;
+        self.foldFourBetCould += other.foldFourBetCould;
+        self.callFourBet += other.callFourBet;
+        self.fiveBet += other.fiveBet;
+        self.fiveBetCould += other.fiveBetCould;
+        self.steal += other.steal;
+        self.stealCould += other.stealCould;
+        self.foldSteal += other.foldSteal;
+        self.foldStealCould += other.foldStealCould;
+        self.cbet += other.cbet;
+        self.cbetCould += other.cbetCould;
+        self.foldCbet += other.foldCbet;
+        self.foldCbetCould += other.foldCbetCould;
+        self.craise += other.craise;
+        self.craiseCould += other.craiseCould;
+        self.donk += other.donk;
+        self.donkCould += other.donkCould;
+        self.turnCraise += other.turnCraise;
+        self.turnCraiseCould += other.turnCraiseCould;
+        self.riverCraise += other.riverCraise;
+        self.riverCraiseCould += other.riverCraiseCould;
+        self.turnDonk += other.turnDonk;
+        self.turnDonkCould += other.turnDonkCould;
+        self.riverDonk += other.riverDonk;
+        self.riverDonkCould += other.riverDonkCould;
+        self.cbetSize += other.cbetSize;
+        self.raiseCbet += other.raiseCbet;
+        self.turnCbet += other.turnCbet;
+        self.turnCbetCould += other.turnCbetCould;
+        self.foldTurnCbet += other.foldTurnCbet;
+        self.foldTurnCbetCould += other.foldTurnCbetCould;
+        self.riverCbet += other.riverCbet;
+        self.riverCbetCould += other.riverCbetCould;
+        self.foldRiverCbet += other.foldRiverCbet;
+        self.foldRiverCbetCould += other.foldRiverCbetCould;
+        self.delayedCbet += other.delayedCbet;
+        self.delayedCbetCould += other.delayedCbetCould;
+        self.openRaise += other.openRaise;
+        self.openRaiseSize += other.openRaiseSize;
+        self.raiseFirstIn += other.raiseFirstIn;
+        self.raiseFirstInCould += other.raiseFirstInCould;
+        self.limp += other.limp;
+        self.limpCould += other.limpCould;
+        self.limpCall += other.limpCall;
+        self.limpCallCould += other.limpCallCould;
+        self.limpRaise += other.limpRaise;
+        self.coldCall += other.coldCall;
+        self.coldCallCould += other.coldCallCould;
+        self.squeeze += other.squeeze;
+        self.squeezeCould += other.squeezeCould;
+        self.isoRaise += other.isoRaise;
+        self.isoRaiseCould += other.isoRaiseCould;
+        self.flopAggression += other.flopAggression;
+        self.flopCall += other.flopCall;
+        self.flopFold += other.flopFold;
+        self.turnAggression += other.turnAggression;
+        self.turnCall += other.turnCall;
+        self.turnFold += other.turnFold;
+        self.riverAggression += other.riverAggression;
+        self.riverCall += other.riverCall;
+        self.riverFold += other.riverFold;
+        self.sawFlop += other.sawFlop;
+        self.showdown += other.showdown;
+        self.showdownWon += other.showdownWon;
+        self.sawFlopWon += other.sawFlopWon;
+        self.net += other.net;
+        self.rake += other.rake;
+        self.netBb += other.netBb;
+        if self.byPosition.len() < other.byPosition.len() {
+            self.byPosition
+                .resize(other.byPosition.len(), Stat::default());
+        }
+        for (stat, other) in self.byPosition.iter_mut().zip(&other.byPosition) {
+            stat.add(other);
+        }
+    }
+
+    // counters of the hands played from position
+    pub fn at(&mut self, position: Position) -> &mut Stat {
+        if self.byPosition.is_empty() {
+            self.byPosition = vec![Stat::default(); Position::ALL.len()];
+        }
+        &mut self.byPosition[position as usize]
+    }
+
+    // counters of one player for one hand
+    pub fn from_hand(hand: &Hand, name: &str) -> Stat {
+        use ActionKind::*;
+
+        let v_button = hand.button_name();
+        let v_sb = hand.poster(PostKind::SmallBlind);
+        let v_bb = hand.poster(PostKind::BigBlind);
+        let v_preflop_callers = hand.actors(Street::Preflop, &[Call]);
+        let v_preflop_raisers = hand.actors(Street::Preflop, &[Bet, Raise]);
+        let v_preflop_folders = hand.actors(Street::Preflop, &[Fold]);
+
+        let pfr = v_preflop_raisers.first().copied().unwrap_or("");
+        let n1 = |b: bool| b as u32;
+
+        let vpip = v_preflop_callers.contains(&name) || v_preflop_raisers.contains(&name);
+
+        // preflop raise levels: 1 is the big blind, 2 the open raise, 3 the 3-bet etc.
+        // faced[level] is what the player did the first time they acted facing that level,
+        // callers[level] how many players had called that level before
+        let mut faced: [Option<ActionKind>; 8] = [None; 8];
+        let mut callers = [0; 8];
+        let mut first = 0; // level faced at the player's first action, 0 if none
+        let (mut level, mut calls) = (1, 0);
+        for a in hand.actions.iter().filter(|a| a.street == Street::Preflop) {
+            if a.name == name && level < faced.len() && faced[level].is_none() {
+                faced[level] = Some(a.kind);
+                callers[level] = calls;
+                if first == 0 {
+                    first = level;
+                }
+            }
+            match a.kind {
+                Raise | Bet => (level, calls) = (level + 1, 0),
+                Call => calls += 1,
+                _ => (),
+            }
+        }
+        let could = |level: usize| faced[level].is_some();
+        let did = |level: usize, kind: ActionKind| faced[level] == Some(kind);
+        let raised = |level: usize| did(level, Raise) || did(level, Bet);
+        // player made that level, ie raised facing the one below
+        let made = |level: usize| raised(level - 1);
+
+        // actions of the player of one of kinds on street
+        let count = |street: Street, kinds: &[ActionKind]| {
+            hand.actions
+                .iter()
+                .filter(|a| a.street == street && a.name == name && kinds.contains(&a.kind))
+                .count() as u32
+        };
+
+        // dealt in and still there when the flop came, sitting out players neither post nor act
+        let dealt = hand.is_dealt(name);
+        let sawFlop = dealt && hand.board.len() >= 3 && !v_preflop_folders.contains(&name);
+        let showdown = sawFlop && hand.showdown.iter().any(|n| n == name);
+        let won = hand.collected(name) > 0;
+
+        let net = hand.net(name);
+        let pots: Chips = hand.collected.iter().map(|c| c.amount).sum();
+        let rake = match pots {
+            0 => 0,
+            _ => hand.rake * hand.collected(name) / pots,
+        };
+        let is_real = hand.money == Money::Real; // other amounts are chips
+
+        let position = hand.position(name);
+        let is_bb = position == Some(Position::Bb); // checks rather than limps
+        let rfiCould = first == 1 && callers[1] == 0 && !is_bb; // only folds before
+        let limp = did(1, Call) && !is_bb;
+        let coldCallCould = first >= 2;
+        let squeezeCould = could(2) && callers[2] > 0;
+        let isoRaiseCould = could(1) && callers[1] > 0;
+
+        // sb folds to btn bet, or bb folds to btn bet
+        let foldStealCould = (name == v_sb && !v_preflop_raisers.is_empty() && pfr == v_button)
+            || (name == v_bb && v_preflop_raisers.len() == 1 && pfr == v_button);
+        let foldSteal = foldStealCould
+            && !v_preflop_raisers.contains(&name)
+            && !v_preflop_callers.contains(&name)
+            && v_preflop_folders.contains(&name);
+
+        // c-bets: the last preflop raiser bets first on the flop, then again on the turn and river
+        // as long as nobody raised him
+        let aggressor = v_preflop_raisers.last().copied().unwrap_or("");
+        let streets = |street: Street| hand.actions.iter().filter(move |a| a.street == street);
+        let raised_on = |street: Street| streets(street).any(|a| a.kind == Raise);
+        // first action of who on street, if nobody bet before it
+        let first_in = |street: Street, who: &str| {
+            streets(street)
+                .take_while(|a| a.name == who || (a.kind != Bet && a.kind != Raise))
+                .find(|a| a.name == who)
+                .map(|a| a.kind)
+        };
+        // what the player did facing the first bet of street, if it was the aggressor's and unraised
+        let facing_cbet = |street: Street| {
+            let mut actions = streets(street).skip_while(|a| a.kind != Bet);
+            if name == aggressor || actions.next()?.name != aggressor {
+                return None;
+            }
+            actions
+                .take_while(|a| a.name == name || a.kind != Raise)
+                .find(|a| a.name == name)
+                .map(|a| a.kind)
+        };
+        let flopCbet = first_in(Street::Flop, aggressor) == Some(Bet);
+        let turnCbet =
+            flopCbet && !raised_on(Street::Flop) && first_in(Street::Turn, aggressor) == Some(Bet);
+        let riverCbet =
+            turnCbet && !raised_on(Street::Turn) && first_in(Street::River, aggressor) == Some(Bet);
+        let is_aggressor = name == aggressor;
+        let cbetCould = is_aggressor && first_in(Street::Flop, name).is_some();
+        let turnCbetCould = is_aggressor
+            && flopCbet
+            && !raised_on(Street::Flop)
+            && first_in(Street::Turn, name).is_some();
+        let riverCbetCould = is_aggressor
+            && turnCbet
+            && !raised_on(Street::Turn)
+            && first_in(Street::River, name).is_some();
+        // checked the flop, and so did everyone
+        let delayedCbetCould = cbetCould
+            && !streets(Street::Flop).any(|a| a.kind == Bet)
+            && first_in(Street::Turn, name).is_some();
+        let foldCbetCould = facing_cbet(Street::Flop).is_some();
+        let foldTurnCbetCould = turnCbet && facing_cbet(Street::Turn).is_some();
+        let foldRiverCbetCould = riverCbet && facing_cbet(Street::River).is_some();
+
+        // checked, then faced a bet: acting again on the street means someone bet
+        let check_raise = |street: Street| {
+            let mut own = streets(street).filter(|a| a.name == name);
+            match (own.next(), own.next()) {
+                (Some(first), Some(next)) if first.kind == Check => (true, next.kind == Raise),
+                _ => (false, false),
+            }
+        };
+        // bet into the last bettor or raiser of the street before, who is still to act
+        let donk_bet = |street: Street, previous: Street| {
+            let aggressor = match streets(previous).rfind(|a| a.kind == Bet || a.kind == Raise) {
+                Some(a) if a.name != name => a.name.as_str(),
+                _ => return (false, false),
+            };
+            let acted = streets(street)
+                .take_while(|a| a.name != name)
+                .any(|a| a.name == aggressor);
+            let to_act = streets(street).any(|a| a.name == aggressor);
+            match first_in(street, name) {
+                Some(kind) if !acted && to_act => (true, kind == Bet),
+                _ => (false, false),
+            }
+        };
+        let (craiseCould, craise) = check_raise(Street::Flop);
+        let (turnCraiseCould, turnCraise) = check_raise(Street::Turn);
+        let (riverCraiseCould, riverCraise) = check_raise(Street::River);
+        let (donkCould, donk) = donk_bet(Street::Flop, Street::Preflop);
+        let (turnDonkCould, turnDonk) = donk_bet(Street::Turn, Street::Flop);
+        let (riverDonkCould, riverDonk) = donk_bet(Street::River, Street::Turn);
+
+        let cbet = is_aggressor && flopCbet;
+        let cbetSize = match hand
+            .actions
+            .iter()
+            .find(|a| cbet && a.street == Street::Flop && a.kind == Bet)
+        {
+            Some(a) => (100 * a.amount / a.pot.max(1)) as u32,
+            None => 0,
+        };
+
+        // first raise preflop, only folds before it
+        let open = hand
+            .actions
+            .iter()
+            .filter(|a| a.street == Street::Preflop)
+            .find(|a| a.kind != Fold)
+            .filter(|a| a.name == name && (a.kind == Raise || a.kind == Bet));
+        let openRaiseSize = match open {
+            Some(a) => (100 * a.to / hand.stakes.bb.max(1)) as u32,
+            None => 0,
+        };
+
+        Stat {
+            handsNo: 1,
+            vpip: n1(vpip),
+            pfr: n1(v_preflop_raisers.contains(&name)),
+            threeBet: n1(raised(2)),
+            threeBetCould: n1(could(2)),
+            foldThreeBet: n1(made(2) && did(3, Fold)),
+            foldThreeBetCould: n1(made(2) && could(3)),
+            callThreeBet: n1(made(2) && did(3, Call)),
+            fourBet: n1(raised(3)),
+            fourBetCould: n1(could(3)),
+            foldFourBet: n1(made(3) && did(4, Fold)),
+            foldFourBetCould: n1(made(3) && could(4)),
+            callFourBet: n1(made(3) && did(4, Call)),
+            fiveBet: n1(raised(4)),
+            fiveBetCould: n1(could(4)),
+            steal: n1(name == v_button && pfr == name),
+            stealCould: n1(name == v_button
+                && (v_preflop_raisers.is_empty() || pfr == v_sb || pfr == v_bb || pfr == v_button)),
+            foldSteal: n1(foldSteal),
+            foldStealCould: n1(foldStealCould),
+            cbet: n1(cbet),
+            cbetCould: n1(cbetCould),
+            foldCbet: n1(facing_cbet(Street::Flop) == Some(Fold)),
+            foldCbetCould: n1(foldCbetCould),
+            craise: n1(craise),
+            craiseCould: n1(craiseCould),
+            donk: n1(donk),
+            donkCould: n1(donkCould),
+            turnCraise: n1(turnCraise),
+            turnCraiseCould: n1(turnCraiseCould),
+            riverCraise: n1(riverCraise),
+            riverCraiseCould: n1(riverCraiseCould),
+            turnDonk: n1(turnDonk),
+            turnDonkCould: n1(turnDonkCould),
+            riverDonk: n1(riverDonk),
+            riverDonkCould: n1(riverDonkCould),
+            cbetSize,
+            raiseCbet: n1(facing_cbet(Street::Flop) == Some(Raise)),
+            turnCbet: n1(is_aggressor && turnCbet),
+            turnCbetCould: n1(turnCbetCould),
+            foldTurnCbet: n1(foldTurnCbetCould && facing_cbet(Street::Turn) == Some(Fold)),
+            foldTurnCbetCould: n1(foldTurnCbetCould),
+            riverCbet: n1(is_aggressor && riverCbet),
+            riverCbetCould: n1(riverCbetCould),
+            foldRiverCbet: n1(foldRiverCbetCould && facing_cbet(Street::River) == Some(Fold)),
+            foldRiverCbetCould: n1(foldRiverCbetCould),
+            delayedCbet: n1(delayedCbetCould && first_in(Street::Turn, name) == Some(Bet)),
+            delayedCbetCould: n1(delayedCbetCould),
+            openRaise: n1(open.is_some()),
+            openRaiseSize,
+            raiseFirstIn: n1(rfiCould && raised(1)),
+            raiseFirstInCould: n1(rfiCould),
+            limp: n1(limp),
+            limpCould: n1(could(1) && !is_bb),
+            limpCall: n1(limp && did(2, Call)),
+            limpCallCould: n1(limp && could(2)),
+            limpRaise: n1(limp && raised(2)),
+            coldCall: n1(coldCallCould && did(first, Call)),
+            coldCallCould: n1(coldCallCould),
+            squeeze: n1(squeezeCould && raised(2)),
+            squeezeCould: n1(squeezeCould),
+            isoRaise: n1(isoRaiseCould && raised(1)),
+            isoRaiseCould: n1(isoRaiseCould),
+            flopAggression: count(Street::Flop, &[Bet, Raise]),
+            flopCall: count(Street::Flop, &[Call]),
+            flopFold: count(Street::Flop, &[Fold]),
+            turnAggression: count(Street::Turn, &[Bet, Raise]),
+            turnCall: count(Street::Turn, &[Call]),
+            turnFold: count(Street::Turn, &[Fold]),
+            riverAggression: count(Street::River, &[Bet, Raise]),
+            riverCall: count(Street::River, &[Call]),
+            riverFold: count(Street::River, &[Fold]),
+            sawFlop: n1(sawFlop),
+            showdown: n1(showdown),
+            showdownWon: n1(showdown && won),
+            sawFlopWon: n1(sawFlop && won),
+            net: if is_real { net } else { 0 },
+            rake: if is_real { rake } else { 0 },
+            netBb: match hand.stakes.bb {
+                0 => 0,
+                bb => net * 100 / bb,
+            },
+            byPosition: Vec::new(),
+        }
+    }
+}
+
+impl Stats {
+    pub fn populate(&mut self, hand: &Hand) {
+        // players dealt in, seated players sitting out don't count
+        for (name, position) in hand.positions() {
+            let stat = Stat::from_hand(hand, name);
+            let total = self.0.entry(name.to_string()).or_default(); // if entry does not exist, push new empty stat
+            total.add(&stat);
+            total.at(position).add(&stat);
+        }
+    }
+
+    // adds counters of other, eg stats computed on another thread
+    pub fn merge(&mut self, other: Stats) {
+        for (name, stat) in other.0 {
+            self.0.entry(name).or_default().add(&stat);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 6 handed, p1 on the button, p2 small blind, p3 big blind
+    fn stats(actions: &[&str]) -> Stats {
+        let mut text = String::from(
+            "PokerStars Hand #1:  Hold'em No Limit ($0.01/$0.02 USD) - 2020/05/30 10:11:12 ET\n\
+             Table 'Test' 6-max Seat #1 is the button\n",
+        );
+        for i in 1..=6 {
+            text += &format!("Seat {}: p{} ($5 in chips)\n", i, i);
+        }
+        text += "p2: posts small blind $0.01\np3: posts big blind $0.02\n*** HOLE CARDS ***\n";
+        for action in actions {
+            text += action;
+            text += "\n";
+        }
+        text += "*** SUMMARY ***\n";
+        let mut stats = Stats::default();
+        stats.populate(&Hand::parse(&text).unwrap());
+        stats
+    }
+
+    #[test]
+    fn raise_levels() {
+        let stats = stats(&[
+            "p4: raises $0.04 to $0.06",
+            "p5: raises $0.12 to $0.18",
+            "p6: folds",
+            "p1: calls $0.18",
+            "p2: folds",
+            "p3: folds",
+            "p4: raises $0.32 to $0.50",
+            "p5: raises $1.50 to $2",
+            "p1: folds",
+            "p4: folds",
+        ]);
+        let p = |name: &str| &stats.0[name];
+
+        // p4 opens, faces the 3-bet and 4-bets, folds to the 5-bet
+        assert_eq!((p("p4").threeBetCould, p("p4").foldThreeBetCould), (0, 1));
+        assert_eq!((p("p4").foldThreeBet, p("p4").callThreeBet), (0, 0));
+        assert_eq!((p("p4").fourBet, p("p4").fourBetCould), (1, 1));
+        assert_eq!((p("p4").fiveBetCould, p("p4").foldFourBetCould), (0, 0));
+
+        // p5 3-bets then 5-bets
+        assert_eq!((p("p5").threeBet, p("p5").threeBetCould), (1, 1));
+        assert_eq!((p("p5").foldFourBet, p("p5").foldFourBetCould), (0, 1));
+        assert_eq!((p("p5").fiveBet, p("p5").fiveBetCould), (1, 1));
+
+        // p6 and the blinds only face the 3-bet, p1 cold calls it then only faces the 5-bet
+        for name in ["p6", "p2", "p3"] {
+            assert_eq!((p(name).threeBetCould, p(name).fourBetCould), (0, 1));
+        }
+        assert_eq!((p("p1").fourBet, p("p1").fourBetCould), (0, 1));
+        assert_eq!((p("p1").fiveBet, p("p1").fiveBetCould), (0, 0));
+        assert_eq!(p("p1").callThreeBet, 0); // not the opener
+    }
+
+    #[test]
+    fn opener_folds_or_calls_3bet() {
+        let folds = stats(&[
+            "p4: raises $0.04 to $0.06",
+            "p5: folds",
+            "p6: raises $0.12 to $0.18",
+            "p1: folds",
+            "p2: folds",
+            "p3: folds",
+            "p4: folds",
+        ]);
+        assert_eq!(
+            (folds.0["p4"].foldThreeBet, folds.0["p4"].foldThreeBetCould),
+            (1, 1)
+        );
+        assert_eq!(
+            (folds.0["p5"].threeBet, folds.0["p5"].threeBetCould),
+            (0, 1)
+        );
+        assert_eq!(
+            (folds.0["p3"].threeBetCould, folds.0["p3"].fourBetCould),
+            (0, 1)
+        );
+
+        let calls = stats(&[
+            "p4: calls $0.02",
+            "p5: raises $0.04 to $0.06",
+            "p6: folds",
+            "p1: folds",
+            "p2: folds",
+            "p4: raises $0.12 to $0.18",
+            "p5: calls $0.12",
+        ]);
+        // limp then raise over the open is a 3-bet, the open raiser calls it
+        assert_eq!(
+            (calls.0["p4"].threeBet, calls.0["p4"].threeBetCould),
+            (1, 1)
+        );
+        assert_eq!(
+            (calls.0["p5"].callThreeBet, calls.0["p5"].foldThreeBetCould),
+            (1, 1)
+        );
+    }
+
+    #[test]
+    fn limps_and_isolation() {
+        let stats = stats(&[
+            "p4: folds",
+            "p5: calls $0.02",
+            "p6: raises $0.06 to $0.08",
+            "p1: calls $0.08",
+            "p2: folds",
+            "p3: folds",
+            "p5: raises $0.24 to $0.32",
+            "p6: folds",
+            "p1: folds",
+        ]);
+        let p = |name: &str| &stats.0[name];
+
+        // p4 folds first in, p5 limps then raises, p6 isolates
+        assert_eq!(p("p4").raiseFirstInCould, 1);
+        assert_eq!((p("p5").raiseFirstIn, p("p5").raiseFirstInCould), (0, 1));
+        assert_eq!(p("p6").raiseFirstInCould, 0);
+        assert_eq!((p("p5").limp, p("p5").limpCould), (1, 1));
+        assert_eq!((p("p5").limpRaise, p("p5").limpCall), (1, 0));
+        assert_eq!(p("p5").limpCallCould, 1);
+        assert_eq!((p("p6").isoRaise, p("p6").isoRaiseCould), (1, 1));
+        assert_eq!((p("p6").limp, p("p6").limpCould), (0, 1));
+
+        // p1 cold calls, the blinds only face the raise and the big blind can't limp
+        assert_eq!((p("p1").coldCall, p("p1").coldCallCould), (1, 1));
+        assert_eq!((p("p2").coldCall, p("p2").coldCallCould), (0, 1));
+        assert_eq!((p("p3").limpCould, p("p3").isoRaiseCould), (0, 0));
+        assert_eq!(p("p3").raiseFirstInCould, 0);
+    }
+
+    #[test]
+    fn open_raise_and_squeeze() {
+        let stats = stats(&[
+            "p4: folds",
+            "p5: folds",
+            "p6: folds",
+            "p1: raises $0.04 to $0.06",
+            "p2: calls $0.05",
+            "p3: raises $0.24 to $0.30",
+            "p1: folds",
+            "p2: folds",
+        ]);
+        let p = |name: &str| &stats.0[name];
+
+        assert_eq!((p("p1").raiseFirstIn, p("p1").raiseFirstInCould), (1, 1));
+        assert_eq!(p("p1").column("RFI:BTN").unwrap(), "100");
+        assert_eq!(p("p1").column("RFI:CO").unwrap(), "0");
+        assert_eq!(p("p6").column("RFI").unwrap(), "0");
+        assert!(p("p1").column("RFI:XX").is_none());
+
+        // the small blind cold calls and can't squeeze, the big blind squeezes
+        assert_eq!((p("p2").coldCall, p("p2").squeezeCould), (1, 0));
+        assert_eq!((p("p3").squeeze, p("p3").squeezeCould), (1, 1));
+        assert_eq!(p("p3").threeBet, 1);
+    }
+
+    #[test]
+    fn counters_by_position() {
+        let mut stats = stats(&[
+            "p4: raises $0.04 to $0.06",
+            "p5: calls $0.06",
+            "p6: folds",
+            "p1: folds",
+            "p2: folds",
+            "p3: folds",
+        ]);
+        // next hand, the button moved to p2
+        let text =
+            "PokerStars Hand #2:  Hold'em No Limit ($0.01/$0.02 USD) - 2020/05/30 10:12:12 ET\n\
+             Table 'Test' 6-max Seat #2 is the button\n\
+             Seat 1: p1 ($5 in chips)\nSeat 2: p2 ($5 in chips)\n\
+             Seat 3: p3 ($5 in chips) is sitting out\nSeat 4: p4 ($5 in chips)\n\
+             p4: posts small blind $0.01\np1: posts big blind $0.02\n*** HOLE CARDS ***\n\
+             p2: raises $0.04 to $0.06\np4: folds\np1: folds\n*** SUMMARY ***\n";
+        stats.populate(&Hand::parse(text).unwrap());
+        let p = |name: &str| &stats.0[name];
+        let at = |name: &str, position: Position| &p(name).byPosition[position as usize];
+
+        assert_eq!(p("p4").handsNo, 2);
+        assert_eq!(p("p3").handsNo, 1); // sitting out
+        for stat in stats.0.values() {
+            let by_position: u32 = stat.byPosition.iter().map(|s| s.handsNo).sum();
+            assert_eq!(by_position, stat.handsNo);
+        }
+        assert_eq!(
+            (at("p4", Position::Utg).pfr, at("p4", Position::Utg).handsNo),
+            (1, 1)
+        );
+        assert_eq!(
+            (at("p4", Position::Sb).pfr, at("p4", Position::Sb).handsNo),
+            (0, 1)
+        );
+        assert_eq!(at("p4", Position::Btn).handsNo, 0);
+        assert_eq!(at("p5", Position::Mp).vpip, 1);
+        assert_eq!(p("p2").column("pfr:BTN").unwrap(), "100");
+        assert_eq!(p("p2").column("pfr:SB").unwrap(), "0");
+        assert_eq!(p("p2").column("pfr").unwrap(), "50");
+        assert!(at("p2", Position::Btn).byPosition.is_empty());
+
+        // positions are added up with the rest
+        let mut total = Stat::default();
+        total.add(p("p4"));
+        total.add(p("p4"));
+        assert_eq!(total.byPosition[Position::Sb as usize].handsNo, 2);
+        assert!(p("p4").column("vpi:XX").is_none());
+        assert_eq!(Stat::default().column("RFI:BB").unwrap(), "0");
+    }
+
+    #[test]
+    fn aggression() {
+        let stats = stats(&[
+            "p4: raises $0.04 to $0.06",
+            "p5: calls $0.06",
+            "p6: folds",
+            "p1: folds",
+            "p2: folds",
+            "p3: calls $0.04",
+            "*** FLOP *** [2c 7h Td]",
+            "p3: checks",
+            "p4: bets $0.10",
+            "p5: raises $0.20 to $0.30",
+            "p3: folds",
+            "p4: calls $0.20",
+            "*** TURN *** [2c 7h Td] [Js]",
+            "p4: checks",
+            "p5: bets $0.50",
+            "p4: calls $0.50",
+            "*** RIVER *** [2c 7h Td Js] [3d]",
+            "p4: bets $1",
+            "p5: folds",
+        ]);
+        let p = |name: &str| &stats.0[name];
+
+        assert_eq!((p("p4").flopAggression, p("p4").flopCall), (1, 1));
+        assert_eq!((p("p4").turnAggression, p("p4").turnCall), (0, 1));
+        assert_eq!((p("p4").riverAggression, p("p5").riverFold), (1, 1));
+        // p4: 2 bets, 2 calls. p5: 2 bets and raises, 1 fold
+        assert_eq!(p("p4").column("AF").unwrap(), "1.0");
+        assert_eq!(p("p4").column("AFq").unwrap(), "50");
+        assert_eq!(p("p4").column("TAF").unwrap(), "0.0");
+        assert_eq!(p("p5").column("AF").unwrap(), "2.0");
+        assert_eq!(p("p5").column("AFq").unwrap(), "66");
+        assert_eq!(p("p5").column("RAFq").unwrap(), "0");
+        assert_eq!(p("p3").column("FAFq").unwrap(), "0");
+        assert_eq!(p("p1").column("AF").unwrap(), "0.0");
+    }
+
+    #[test]
+    fn showdown() {
+        let called = stats(&[
+            "p4: raises $0.04 to $0.06",
+            "p5: calls $0.06",
+            "p6: calls $0.06",
+            "p1: folds",
+            "p2: folds",
+            "p3: folds",
+            "*** FLOP *** [2c 7h Td]",
+            "p4: bets $0.10",
+            "p5: calls $0.10",
+            "p6: folds",
+            "*** TURN *** [2c 7h Td] [Js]",
+            "p4: checks",
+            "p5: checks",
+            "*** RIVER *** [2c 7h Td Js] [3d]",
+            "p4: checks",
+            "p5: checks",
+            "*** SHOW DOWN ***",
+            "p4: shows [Ah Ad] (a pair of Aces)",
+            "p5: mucks hand",
+            "p4 collected $0.41 from pot",
+        ]);
+        let p = |name: &str| &called.0[name];
+
+        for name in ["p4", "p5", "p6"] {
+            assert_eq!(p(name).sawFlop, 1);
+        }
+        assert_eq!((p("p3").sawFlop, p("p3").showdown), (0, 0));
+        assert_eq!((p("p4").showdown, p("p4").showdownWon), (1, 1));
+        assert_eq!((p("p5").showdown, p("p5").showdownWon), (1, 0));
+        assert_eq!((p("p6").showdown, p("p6").sawFlopWon), (0, 0));
+        assert_eq!(p("p4").column("WWSF").unwrap(), "100");
+        assert_eq!(p("p5").column("WTSD").unwrap(), "100");
+        assert_eq!(p("p5").column("W$SD").unwrap(), "0");
+
+        // won without a showdown
+        let folded = stats(&[
+            "p4: raises $0.04 to $0.06",
+            "p5: folds",
+            "p6: folds",
+            "p1: folds",
+            "p2: folds",
+            "p3: calls $0.04",
+            "*** FLOP *** [2c 7h Td]",
+            "p3: checks",
+            "p4: bets $0.10",
+            "p3: folds",
+            "Uncalled bet ($0.10) returned to p4",
+            "p4 collected $0.13 from pot",
+        ]);
+        let p4 = &folded.0["p4"];
+        assert_eq!((p4.sawFlopWon, p4.showdown), (1, 0));
+        assert_eq!(p4.column("W$SD").unwrap(), "0");
+    }
+
+    #[test]
+    fn net_and_rake() {
+        let mut stats = stats(&[
+            "p4: raises $0.04 to $0.06",
+            "p5: folds",
+            "p6: folds",
+            "p1: folds",
+            "p2: folds",
+            "p3: calls $0.04",
+            "*** FLOP *** [2c 7h Td]",
+            "p3: checks",
+            "p4: bets $0.10",
+            "p3: calls $0.10",
+            "*** SHOW DOWN ***",
+            "p4: shows [Ah Ad] (a pair of Aces)",
+            "p3: mucks hand",
+            "p4 collected $0.31 from pot",
+            "*** SUMMARY ***",
+            "Total pot $0.33 | Rake $0.02",
+        ]);
+        let p = |stats: &Stats, name: &str| stats.0[name].clone();
+
+        assert_eq!((p(&stats, "p4").net, p(&stats, "p4").rake), (15, 2));
+        assert_eq!((p(&stats, "p3").net, p(&stats, "p3").rake), (-16, 0));
+        assert_eq!(p(&stats, "p2").net, -1);
+        assert_eq!(p(&stats, "p4").netBb, 750);
+        assert_eq!(p(&stats, "p4").column("Net").unwrap(), "0.15");
+        assert_eq!(p(&stats, "p3").column("bb100").unwrap(), "-800.0");
+
+        // a play money hand only counts in bb/100
+        let text = "PokerStars Hand #2:  Hold'em No Limit (10/20) - 2020/05/30 10:12:12 ET\n\
+             Table 'Test' 6-max (Play Money) Seat #2 is the button\n\
+             Seat 2: p2 (1000 in chips)\nSeat 4: p4 (1000 in chips)\n\
+             p2: posts small blind 10\np4: posts big blind 20\n*** HOLE CARDS ***\n\
+             p2: folds\nUncalled bet (10) returned to p4\np4 collected 20 from pot\n\
+             *** SUMMARY ***\nTotal pot 20 | Rake 0\n";
+        stats.populate(&Hand::parse(text).unwrap());
+        assert_eq!(p(&stats, "p4").net, 15);
+        assert_eq!(p(&stats, "p4").netBb, 800);
+        assert_eq!(p(&stats, "p4").column("bb100").unwrap(), "400.0");
+    }
+
+    // p4 opens, p5 and the big blind call
+    fn postflop(actions: &[&str]) -> Stats {
+        let mut all = vec![
+            "p4: raises $0.04 to $0.06",
+            "p5: calls $0.06",
+            "p6: folds",
+            "p1: folds",
+            "p2: folds",
+            "p3: calls $0.04",
+        ];
+        all.extend(actions);
+        stats(&all)
+    }
+
+    #[test]
+    fn cbets_and_barrels() {
+        let stats = postflop(&[
+            "*** FLOP *** [2c 7h Td]",
+            "p3: checks",
+            "p4: bets $0.10",
+            "p5: folds",
+            "p3: calls $0.10",
+            "*** TURN *** [2c 7h Td] [Js]",
+            "p3: checks",
+            "p4: bets $0.20",
+            "p3: calls $0.20",
+            "*** RIVER *** [2c 7h Td Js] [3d]",
+            "p3: checks",
+            "p4: bets $0.50",
+            "p3: folds",
+        ]);
+        let p = |name: &str| &stats.0[name];
+
+        assert_eq!((p("p4").cbet, p("p4").cbetCould), (1, 1));
+        assert_eq!((p("p4").turnCbet, p("p4").turnCbetCould), (1, 1));
+        assert_eq!((p("p4").riverCbet, p("p4").riverCbetCould), (1, 1));
+        assert_eq!((p("p4").foldCbetCould, p("p4").delayedCbetCould), (0, 0));
+        assert_eq!((p("p5").foldCbet, p("p5").foldCbetCould), (1, 1));
+        assert_eq!((p("p3").foldCbet, p("p3").foldCbetCould), (0, 1));
+        assert_eq!((p("p3").foldTurnCbet, p("p3").foldTurnCbetCould), (0, 1));
+        assert_eq!((p("p3").foldRiverCbet, p("p3").foldRiverCbetCould), (1, 1));
+        assert_eq!(p("p5").foldTurnCbetCould, 0);
+    }
+
+    #[test]
+    fn raised_cbet_ends_barrels() {
+        let stats = postflop(&[
+            "*** FLOP *** [2c 7h Td]",
+            "p3: checks",
+            "p4: bets $0.10",
+            "p5: raises $0.20 to $0.30",
+            "p3: folds",
+            "p4: calls $0.20",
+            "*** TURN *** [2c 7h Td] [Js]",
+            "p4: bets $0.50",
+            "p5: folds",
+        ]);
+        let p = |name: &str| &stats.0[name];
+
+        assert_eq!((p("p5").raiseCbet, p("p5").foldCbetCould), (1, 1));
+        // p3 faced the raise, not the c-bet alone
+        assert_eq!((p("p3").foldCbet, p("p3").foldCbetCould), (0, 0));
+        assert_eq!((p("p4").turnCbet, p("p4").turnCbetCould), (0, 0));
+        assert_eq!((p("p5").foldTurnCbet, p("p5").foldTurnCbetCould), (0, 0));
+    }
+
+    #[test]
+    fn no_cbet_when_bet_into() {
+        let led = postflop(&[
+            "*** FLOP *** [2c 7h Td]",
+            "p3: bets $0.10",
+            "p4: raises $0.20 to $0.30",
+            "p5: folds",
+            "p3: folds",
+        ]);
+        assert_eq!((led.0["p4"].cbet, led.0["p4"].cbetCould), (0, 0));
+        assert_eq!(led.0["p5"].foldCbetCould, 0);
+
+        // the flop checked through, then a delayed c-bet
+        let delayed = postflop(&[
+            "*** FLOP *** [2c 7h Td]",
+            "p3: checks",
+            "p4: checks",
+            "p5: checks",
+            "*** TURN *** [2c 7h Td] [Js]",
+            "p3: checks",
+            "p4: bets $0.10",
+            "p5: folds",
+            "p3: folds",
+        ]);
+        let p4 = &delayed.0["p4"];
+        assert_eq!((p4.cbet, p4.cbetCould), (0, 1));
+        assert_eq!((p4.delayedCbet, p4.delayedCbetCould), (1, 1));
+        assert_eq!(
+            (p4.turnCbetCould, delayed.0["p5"].foldTurnCbetCould),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn cbet_by_the_3bettor() {
+        let stats = stats(&[
+            "p4: raises $0.04 to $0.06",
+            "p5: raises $0.12 to $0.18",
+            "p6: folds",
+            "p1: folds",
+            "p2: folds",
+            "p3: folds",
+            "p4: calls $0.12",
+            "*** FLOP *** [2c 7h Td]",
+            "p4: checks",
+            "p5: bets $0.20",
+            "p4: folds",
+        ]);
+        assert_eq!((stats.0["p5"].cbet, stats.0["p5"].cbetCould), (1, 1));
+        assert_eq!(stats.0["p4"].cbetCould, 0);
+        assert_eq!(
+            (stats.0["p4"].foldCbet, stats.0["p4"].foldCbetCould),
+            (1, 1)
+        );
+    }
+
+    // check-raise and donk bets, in postflop() the big blind p3 acts first, then the opener p4, then p5
+
+    #[test]
+    fn check_raise_when_bet_into() {
+        let stats = postflop(&[
+            "*** FLOP *** [2c 7h Td]",
+            "p3: checks",
+            "p4: bets $0.10",
+            "p5: folds",
+            "p3: raises $0.20 to $0.30",
+            "p4: folds",
+        ]);
+        assert_eq!((stats.0["p3"].craise, stats.0["p3"].craiseCould), (1, 1));
+        assert_eq!(stats.0["p5"].craiseCould, 0); // didn't check
+        assert_eq!(stats.0["p4"].craiseCould, 0);
+    }
+
+    #[test]
+    fn check_call_and_check_behind() {
+        let called = postflop(&[
+            "*** FLOP *** [2c 7h Td]",
+            "p3: checks",
+            "p4: checks",
+            "p5: bets $0.10",
+            "p3: calls $0.10",
+            "p4: raises $0.20 to $0.30",
+            "p5: folds",
+            "p3: folds",
+        ]);
+        let p = |name: &str| &called.0[name];
+        assert_eq!((p("p3").craise, p("p3").craiseCould), (0, 1));
+        assert_eq!((p("p4").craise, p("p4").craiseCould), (1, 1));
+        assert_eq!(p("p5").craiseCould, 0);
+
+        // nobody bet, checking wasn't a chance to check-raise
+        let checked = postflop(&[
+            "*** FLOP *** [2c 7h Td]",
+            "p3: checks",
+            "p4: checks",
+            "p5: checks",
+        ]);
+        for name in ["p3", "p4", "p5"] {
+            assert_eq!(checked.0[name].craiseCould, 0);
+        }
+    }
+
+    #[test]
+    fn check_raise_on_turn_and_river() {
+        let stats = postflop(&[
+            "*** FLOP *** [2c 7h Td]",
+            "p3: checks",
+            "p4: bets $0.10",
+            "p5: folds",
+            "p3: calls $0.10",
+            "*** TURN *** [2c 7h Td] [Js]",
+            "p3: checks",
+            "p4: bets $0.20",
+            "p3: raises $0.40 to $0.60",
+            "p4: calls $0.40",
+            "*** RIVER *** [2c 7h Td Js] [3d]",
+            "p3: checks",
+            "p4: checks",
+        ]);
+        let p3 = &stats.0["p3"];
+        assert_eq!((p3.craise, p3.craiseCould), (0, 1));
+        assert_eq!((p3.turnCraise, p3.turnCraiseCould), (1, 1));
+        assert_eq!((p3.riverCraise, p3.riverCraiseCould), (0, 0));
+        assert_eq!(stats.0["p4"].riverCraiseCould, 0);
+    }
+
+    #[test]
+    fn donk_into_the_preflop_raiser() {
+        let stats = postflop(&[
+            "*** FLOP *** [2c 7h Td]",
+            "p3: bets $0.10",
+            "p4: calls $0.10",
+            "p5: folds",
+        ]);
+        let p = |name: &str| &stats.0[name];
+        assert_eq!((p("p3").donk, p("p3").donkCould), (1, 1));
+        assert_eq!(p("p4").donkCould, 0); // the raiser himself
+        assert_eq!(p("p5").donkCould, 0); // acts after the raiser
+
+        // checking to the raiser is the other choice
+        let checked = postflop(&[
+            "*** FLOP *** [2c 7h Td]",
+            "p3: checks",
+            "p4: checks",
+            "p5: bets $0.10",
+            "p3: folds",
+            "p4: folds",
+        ]);
+        assert_eq!((checked.0["p3"].donk, checked.0["p3"].donkCould), (0, 1));
+        assert_eq!((checked.0["p5"].donk, checked.0["p5"].donkCould), (0, 0));
+    }
+
+    #[test]
+    fn no_donk_chance_after_a_bet() {
+        // the small blind leads first, the big blind then faces a bet
+        let stats = stats(&[
+            "p4: raises $0.04 to $0.06",
+            "p5: folds",
+            "p6: folds",
+            "p1: folds",
+            "p2: calls $0.05",
+            "p3: calls $0.04",
+            "*** FLOP *** [2c 7h Td]",
+            "p2: bets $0.10",
+            "p3: raises $0.20 to $0.30",
+            "p4: folds",
+            "p2: folds",
+        ]);
+        assert_eq!((stats.0["p2"].donk, stats.0["p2"].donkCould), (1, 1));
+        assert_eq!((stats.0["p3"].donk, stats.0["p3"].donkCould), (0, 0));
+    }
+
+    #[test]
+    fn donk_from_the_big_blind_after_a_steal() {
+        let stats = stats(&[
+            "p4: folds",
+            "p5: folds",
+            "p6: folds",
+            "p1: raises $0.04 to $0.06",
+            "p2: folds",
+            "p3: calls $0.04",
+            "*** FLOP *** [2c 7h Td]",
+            "p3: bets $0.10",
+            "p1: folds",
+        ]);
+        assert_eq!((stats.0["p3"].donk, stats.0["p3"].donkCould), (1, 1));
+        assert_eq!(stats.0["p1"].donkCould, 0);
+    }
+
+    #[test]
+    fn donk_on_turn_and_river() {
+        let stats = postflop(&[
+            "*** FLOP *** [2c 7h Td]",
+            "p3: checks",
+            "p4: checks",
+            "p5: bets $0.10",
+            "p3: calls $0.10",
+            "p4: calls $0.10",
+            "*** TURN *** [2c 7h Td] [Js]",
+            "p3: bets $0.30",
+            "p4: folds",
+            "p5: calls $0.30",
+            "*** RIVER *** [2c 7h Td Js] [3d]",
+            "p3: checks",
+            "p5: checks",
+        ]);
+        let p = |name: &str| &stats.0[name];
+        // p5 made the flop bet, p3 bets the turn into him
+        assert_eq!((p("p3").turnDonk, p("p3").turnDonkCould), (1, 1));
+        assert_eq!(p("p4").turnDonkCould, 0); // faced a bet
+        assert_eq!(p("p5").turnDonkCould, 0);
+        // p3 made the turn bet, nobody can donk into himself
+        assert_eq!((p("p3").riverDonkCould, p("p5").riverDonkCould), (0, 0));
+
+        // a checked flop has no aggressor to donk into on the turn
+        let checked = postflop(&[
+            "*** FLOP *** [2c 7h Td]",
+            "p3: checks",
+            "p4: checks",
+            "p5: checks",
+            "*** TURN *** [2c 7h Td] [Js]",
+            "p3: bets $0.10",
+            "p4: folds",
+            "p5: folds",
+        ]);
+        assert_eq!(checked.0["p3"].turnDonkCould, 0);
+    }
+
+    #[test]
+    fn river_donk_chance_checked() {
+        let stats = postflop(&[
+            "*** FLOP *** [2c 7h Td]",
+            "p3: checks",
+            "p4: bets $0.10",
+            "p5: folds",
+            "p3: calls $0.10",
+            "*** TURN *** [2c 7h Td] [Js]",
+            "p3: checks",
+            "p4: bets $0.20",
+            "p3: calls $0.20",
+            "*** RIVER *** [2c 7h Td Js] [3d]",
+            "p3: checks",
+            "p4: checks",
+        ]);
+        let p3 = &stats.0["p3"];
+        assert_eq!((p3.turnDonk, p3.turnDonkCould), (0, 1));
+        assert_eq!((p3.riverDonk, p3.riverDonkCould), (0, 1));
+        assert_eq!(p3.column("RDk").unwrap(), "0");
+    }
+}
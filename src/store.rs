@@ -0,0 +1,48 @@
+// optional sqlite copy of every hand, built with the sqlite cargo feature.
+// without it Store can't be opened, so the rest of the program is the same either way
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "sqlite")]
+pub use sqlite::Store;
+
+#[cfg(not(feature = "sqlite"))]
+mod disabled {
+    use crate::hand::Hand;
+    use crate::stats::Stats;
+    use std::borrow::Borrow;
+    use std::path::Path;
+
+    #[derive(Debug)]
+    pub enum Store {}
+
+    impl Store {
+        pub fn open(path: &Path) -> Result<Store, String> {
+            Err(format!(
+                "{}: built without sqlite support, rebuild with --features sqlite",
+                path.display()
+            ))
+        }
+
+        pub fn insert(
+            &mut self,
+            _hands: impl IntoIterator<Item = impl Borrow<Hand>>,
+        ) -> Result<(), String> {
+            match *self {}
+        }
+
+        pub fn save_stats(&self, _stats: &Stats) -> Result<(), String> {
+            match *self {}
+        }
+
+        pub fn outdated_hands(&self) -> Result<usize, String> {
+            match *self {}
+        }
+
+        pub fn for_each_hand(&self, _f: impl FnMut(Hand)) -> Result<(), String> {
+            match *self {}
+        }
+    }
+}
+#[cfg(not(feature = "sqlite"))]
+pub use disabled::Store;
@@ -0,0 +1,550 @@
+// hands, players, seats, posts, actions, showdowns, pots collected and uncalled bets returned,
+// one row each, for ad-hoc queries and for computing stats again after their definitions change.
+// stats holds the current counters, one column per Stat field, rewritten at every database save.
+// amounts are in hundredths like in hand.rs, enums are stored by name
+
+use crate::hand::{
+    Action, ActionKind, Collected, Hand, Money, Position, Post, PostKind, Seat, Stakes, Street,
+};
+use crate::stats::{Stat, Stats};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, Value, ValueRef};
+use rusqlite::{params, params_from_iter, Connection};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::iter;
+use std::path::Path;
+
+const K_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS hands (
+    id INTEGER PRIMARY KEY, -- PokerStars hand number
+    date TEXT NOT NULL,     -- 2020-05-30 04:11:12, eastern time
+    game TEXT NOT NULL,
+    money TEXT NOT NULL,
+    sb INTEGER NOT NULL,
+    bb INTEGER NOT NULL,
+    table_name TEXT NOT NULL,
+    max_seats INTEGER NOT NULL,
+    button INTEGER NOT NULL, -- seat number
+    board TEXT NOT NULL DEFAULT '', -- 2c 7h Td Js 3d
+    pot INTEGER NOT NULL DEFAULT 0,
+    rake INTEGER NOT NULL DEFAULT 0,
+    jackpot INTEGER NOT NULL DEFAULT 0,
+    version INTEGER NOT NULL DEFAULT 1 -- K_HAND_VERSION when stored
+);
+CREATE INDEX IF NOT EXISTS hands_date ON hands (date);
+CREATE TABLE IF NOT EXISTS players (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+CREATE TABLE IF NOT EXISTS seats (
+    hand INTEGER NOT NULL REFERENCES hands (id),
+    seat INTEGER NOT NULL,
+    player INTEGER NOT NULL REFERENCES players (id),
+    stack INTEGER NOT NULL,
+    PRIMARY KEY (hand, seat)
+);
+CREATE INDEX IF NOT EXISTS seats_player ON seats (player);
+CREATE TABLE IF NOT EXISTS posts (
+    hand INTEGER NOT NULL REFERENCES hands (id),
+    seq INTEGER NOT NULL,
+    player INTEGER NOT NULL REFERENCES players (id),
+    kind TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    is_all_in INTEGER NOT NULL,
+    PRIMARY KEY (hand, seq)
+);
+CREATE TABLE IF NOT EXISTS actions (
+    hand INTEGER NOT NULL REFERENCES hands (id),
+    seq INTEGER NOT NULL, -- order of play
+    street TEXT NOT NULL,
+    player INTEGER NOT NULL REFERENCES players (id),
+    kind TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    to_amount INTEGER NOT NULL,
+    pot INTEGER NOT NULL,
+    stack INTEGER NOT NULL,
+    is_all_in INTEGER NOT NULL,
+    PRIMARY KEY (hand, seq)
+);
+CREATE TABLE IF NOT EXISTS showdowns (
+    hand INTEGER NOT NULL REFERENCES hands (id),
+    player INTEGER NOT NULL REFERENCES players (id),
+    PRIMARY KEY (hand, player)
+);
+CREATE TABLE IF NOT EXISTS collected (
+    hand INTEGER NOT NULL REFERENCES hands (id),
+    seq INTEGER NOT NULL,
+    player INTEGER NOT NULL REFERENCES players (id),
+    amount INTEGER NOT NULL,
+    PRIMARY KEY (hand, seq)
+);
+CREATE TABLE IF NOT EXISTS returned ( -- uncalled bets
+    hand INTEGER NOT NULL REFERENCES hands (id),
+    seq INTEGER NOT NULL,
+    player INTEGER NOT NULL REFERENCES players (id),
+    amount INTEGER NOT NULL,
+    PRIMARY KEY (hand, seq)
+);
+";
+
+// columns added to the tables of older stores, CREATE TABLE IF NOT EXISTS leaves them as they are.
+// hands stored before have the default value
+const K_ADDED_COLUMNS: [(&str, &str, &str); 5] = [
+    ("hands", "board", "TEXT NOT NULL DEFAULT ''"),
+    ("hands", "pot", "INTEGER NOT NULL DEFAULT 0"),
+    ("hands", "rake", "INTEGER NOT NULL DEFAULT 0"),
+    ("hands", "jackpot", "INTEGER NOT NULL DEFAULT 0"),
+    ("hands", "version", "INTEGER NOT NULL DEFAULT 1"),
+];
+
+// what is stored of a hand, hands stored by an older version miss data and can't be counted again.
+// 1: seats, posts and actions
+// 2: board, showdowns, pots collected, uncalled bets returned, pot, rake and jackpot
+const K_HAND_VERSION: i64 = 2;
+
+macro_rules! text_enum {
+    ($t:ident { $($v:ident),* }) => {
+        impl ToSql for $t {
+            fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+                Ok(ToSqlOutput::from(match self {
+                    $($t::$v => stringify!($v),)*
+                }))
+            }
+        }
+
+        impl FromSql for $t {
+            fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+                match value.as_str()? {
+                    $(stringify!($v) => Ok($t::$v),)*
+                    _ => Err(FromSqlError::InvalidType),
+                }
+            }
+        }
+    };
+}
+
+text_enum!(Money {
+    Real,
+    Play,
+    Tournament
+});
+text_enum!(PostKind {
+    SmallBlind,
+    BigBlind,
+    SmallAndBig,
+    Ante
+});
+text_enum!(Street {
+    Preflop,
+    Flop,
+    Turn,
+    River
+});
+text_enum!(ActionKind {
+    Fold,
+    Check,
+    Call,
+    Bet,
+    Raise
+});
+
+#[derive(Debug)]
+pub struct Store {
+    conn: Connection,
+    players: HashMap<String, i64>, // ids of players already stored
+}
+
+// id of a player, stored if new
+fn player_id(
+    conn: &Connection,
+    players: &mut HashMap<String, i64>,
+    name: &str,
+) -> rusqlite::Result<i64> {
+    if let Some(id) = players.get(name) {
+        return Ok(*id);
+    }
+    conn.prepare_cached("INSERT OR IGNORE INTO players (name) VALUES (?1)")?
+        .execute([name])?;
+    let id = conn
+        .prepare_cached("SELECT id FROM players WHERE name = ?1")?
+        .query_row([name], |row| row.get(0))?;
+    players.insert(name.to_string(), id);
+    Ok(id)
+}
+
+fn collected_row(r: &rusqlite::Row) -> rusqlite::Result<Collected> {
+    Ok(Collected {
+        name: r.get(0)?,
+        amount: r.get(1)?,
+    })
+}
+
+// Stat field names and values, in a fixed order, then the same by position, eg vpip_BTN
+fn counters(stat: &Stat) -> Vec<(String, i64)> {
+    let fields = |stat: &Stat| -> Vec<(String, i64)> {
+        match serde_json::to_value(stat) {
+            Ok(serde_json::Value::Object(fields)) => fields
+                .into_iter()
+                .filter_map(|(name, value)| Some((name, value.as_i64()?))) // not byPosition
+                .collect(),
+            _ => Vec::new(),
+        }
+    };
+    let mut counters = fields(stat);
+    for (i, position) in Position::ALL.iter().enumerate() {
+        let stat = stat.byPosition.get(i).cloned().unwrap_or_default();
+        counters.extend(
+            fields(&stat)
+                .into_iter()
+                .map(|(name, value)| (format!("{}_{}", name, position.name()), value)),
+        );
+    }
+    counters
+}
+
+impl Store {
+    pub fn open(path: &Path) -> Result<Store, String> {
+        let open = || -> rusqlite::Result<Store> {
+            let conn = Connection::open(path)?;
+            conn.execute_batch(K_SCHEMA)?;
+            for (table, column, definition) in K_ADDED_COLUMNS {
+                let exists: bool = conn.query_row(
+                    "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+                    [table, column],
+                    |row| row.get(0),
+                )?;
+                if !exists {
+                    conn.execute_batch(&format!(
+                        "ALTER TABLE {} ADD COLUMN {} {}",
+                        table, column, definition
+                    ))?;
+                }
+            }
+            Ok(Store {
+                conn,
+                players: HashMap::new(),
+            })
+        };
+        open().map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // in one transaction, hands already stored are left as they are
+    pub fn insert(
+        &mut self,
+        hands: impl IntoIterator<Item = impl Borrow<Hand>>,
+    ) -> Result<(), String> {
+        let result = self.insert_hands(hands);
+        if result.is_err() {
+            self.players.clear(); // ids of the rolled back transaction are gone
+        }
+        result.map_err(|e| format!("sqlite: {}", e))
+    }
+
+    fn insert_hands(
+        &mut self,
+        hands: impl IntoIterator<Item = impl Borrow<Hand>>,
+    ) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        for hand in hands {
+            let hand = hand.borrow();
+            let id = hand.id as i64;
+            let inserted = tx
+                // columns named, added ones come in the order older stores were upgraded
+                .prepare_cached(
+                    "INSERT OR IGNORE INTO hands (id, date, game, money, sb, bb, table_name,
+                     max_seats, button, board, pot, rake, jackpot, version)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                )?
+                .execute(params![
+                    id,
+                    hand.date,
+                    hand.game,
+                    hand.money,
+                    hand.stakes.sb,
+                    hand.stakes.bb,
+                    hand.table,
+                    hand.max_seats,
+                    hand.button,
+                    hand.board.join(" "),
+                    hand.pot,
+                    hand.rake,
+                    hand.jackpot,
+                    K_HAND_VERSION
+                ])?;
+            if inserted == 0 {
+                continue; // already stored
+            }
+            for seat in &hand.seats {
+                let player = player_id(&tx, &mut self.players, &seat.name)?;
+                tx.prepare_cached("INSERT INTO seats VALUES (?1, ?2, ?3, ?4)")?
+                    .execute(params![id, seat.seat, player, seat.stack])?;
+            }
+            for (seq, post) in hand.posts.iter().enumerate() {
+                let player = player_id(&tx, &mut self.players, &post.name)?;
+                tx.prepare_cached("INSERT INTO posts VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?
+                    .execute(params![
+                        id,
+                        seq,
+                        player,
+                        post.kind,
+                        post.amount,
+                        post.is_all_in
+                    ])?;
+            }
+            for (seq, a) in hand.actions.iter().enumerate() {
+                let player = player_id(&tx, &mut self.players, &a.name)?;
+                tx.prepare_cached(
+                    "INSERT INTO actions VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                )?
+                .execute(params![
+                    id,
+                    seq,
+                    a.street,
+                    player,
+                    a.kind,
+                    a.amount,
+                    a.to,
+                    a.pot,
+                    a.stack,
+                    a.is_all_in
+                ])?;
+            }
+            for name in &hand.showdown {
+                let player = player_id(&tx, &mut self.players, name)?;
+                tx.prepare_cached("INSERT INTO showdowns VALUES (?1, ?2)")?
+                    .execute(params![id, player])?;
+            }
+            for (seq, c) in hand.collected.iter().enumerate() {
+                let player = player_id(&tx, &mut self.players, &c.name)?;
+                tx.prepare_cached("INSERT INTO collected VALUES (?1, ?2, ?3, ?4)")?
+                    .execute(params![id, seq, player, c.amount])?;
+            }
+            for (seq, r) in hand.returned.iter().enumerate() {
+                let player = player_id(&tx, &mut self.players, &r.name)?;
+                tx.prepare_cached("INSERT INTO returned VALUES (?1, ?2, ?3, ?4)")?
+                    .execute(params![id, seq, player, r.amount])?;
+            }
+        }
+        tx.commit()
+    }
+
+    // hands stored by an older version, without all the data of a hand
+    pub fn outdated_hands(&self) -> Result<usize, String> {
+        self.conn
+            .query_row(
+                "SELECT COUNT(*) FROM hands WHERE version < ?1",
+                [K_HAND_VERSION],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("sqlite: {}", e))
+    }
+
+    // every stored hand, in hand number order
+    pub fn for_each_hand(&self, f: impl FnMut(Hand)) -> Result<(), String> {
+        self.read_hands(f).map_err(|e| format!("sqlite: {}", e))
+    }
+
+    fn read_hands(&self, mut f: impl FnMut(Hand)) -> rusqlite::Result<()> {
+        let mut seats = self.conn.prepare(
+            "SELECT seat, name, stack FROM seats JOIN players ON players.id = player
+             WHERE hand = ?1 ORDER BY seat",
+        )?;
+        let mut posts = self.conn.prepare(
+            "SELECT name, kind, amount, is_all_in FROM posts JOIN players ON players.id = player
+             WHERE hand = ?1 ORDER BY seq",
+        )?;
+        let mut actions = self.conn.prepare(
+            "SELECT street, name, kind, amount, to_amount, pot, stack, is_all_in
+             FROM actions JOIN players ON players.id = player WHERE hand = ?1 ORDER BY seq",
+        )?;
+        // in the order they were shown
+        let mut showdowns = self.conn.prepare(
+            "SELECT name FROM showdowns JOIN players ON players.id = player
+             WHERE hand = ?1 ORDER BY showdowns.rowid",
+        )?;
+        let mut collected = self.conn.prepare(
+            "SELECT name, amount FROM collected JOIN players ON players.id = player
+             WHERE hand = ?1 ORDER BY seq",
+        )?;
+        let mut returned = self.conn.prepare(
+            "SELECT name, amount FROM returned JOIN players ON players.id = player
+             WHERE hand = ?1 ORDER BY seq",
+        )?;
+        let mut hands = self.conn.prepare(
+            "SELECT id, date, game, money, sb, bb, table_name, max_seats, button, board, pot, rake,
+             jackpot FROM hands ORDER BY id",
+        )?;
+        let mut rows = hands.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            let hand = Hand {
+                id: id as u64,
+                date: row.get(1)?,
+                game: row.get(2)?,
+                money: row.get(3)?,
+                stakes: Stakes {
+                    sb: row.get(4)?,
+                    bb: row.get(5)?,
+                },
+                table: row.get(6)?,
+                max_seats: row.get(7)?,
+                button: row.get(8)?,
+                seats: seats
+                    .query_map([id], |r| {
+                        Ok(Seat {
+                            seat: r.get(0)?,
+                            name: r.get(1)?,
+                            stack: r.get(2)?,
+                        })
+                    })?
+                    .collect::<rusqlite::Result<_>>()?,
+                posts: posts
+                    .query_map([id], |r| {
+                        Ok(Post {
+                            name: r.get(0)?,
+                            kind: r.get(1)?,
+                            amount: r.get(2)?,
+                            is_all_in: r.get(3)?,
+                        })
+                    })?
+                    .collect::<rusqlite::Result<_>>()?,
+                actions: actions
+                    .query_map([id], |r| {
+                        Ok(Action {
+                            street: r.get(0)?,
+                            name: r.get(1)?,
+                            kind: r.get(2)?,
+                            amount: r.get(3)?,
+                            to: r.get(4)?,
+                            pot: r.get(5)?,
+                            stack: r.get(6)?,
+                            is_all_in: r.get(7)?,
+                        })
+                    })?
+                    .collect::<rusqlite::Result<_>>()?,
+                board: row
+                    .get::<_, String>(9)?
+                    .split_whitespace()
+                    .map(|c| c.to_string())
+                    .collect(),
+                showdown: showdowns
+                    .query_map([id], |r| r.get(0))?
+                    .collect::<rusqlite::Result<_>>()?,
+                collected: collected
+                    .query_map([id], collected_row)?
+                    .collect::<rusqlite::Result<_>>()?,
+                returned: returned
+                    .query_map([id], collected_row)?
+                    .collect::<rusqlite::Result<_>>()?,
+                pot: row.get(10)?,
+                rake: row.get(11)?,
+                jackpot: row.get(12)?,
+            };
+            f(hand);
+        }
+        Ok(())
+    }
+
+    // replaces the stats table, one row per player and one column per Stat field
+    pub fn save_stats(&self, stats: &Stats) -> Result<(), String> {
+        self.write_stats(stats)
+            .map_err(|e| format!("sqlite: {}", e))
+    }
+
+    fn write_stats(&self, stats: &Stats) -> rusqlite::Result<()> {
+        let columns: Vec<String> = counters(&Stat::default())
+            .into_iter()
+            .map(|(name, _)| format!("\"{}\" INTEGER NOT NULL", name))
+            .collect();
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute_batch(&format!(
+            "DROP TABLE IF EXISTS stats; CREATE TABLE stats (player TEXT PRIMARY KEY, {});",
+            columns.join(", ")
+        ))?;
+        {
+            let mut insert = tx.prepare(&format!(
+                "INSERT INTO stats VALUES (?{})",
+                ", ?".repeat(columns.len())
+            ))?;
+            for (name, stat) in &stats.0 {
+                let values = counters(stat).into_iter().map(|(_, v)| Value::Integer(v));
+                insert.execute(params_from_iter(
+                    iter::once(Value::Text(name.clone())).chain(values),
+                ))?;
+            }
+        }
+        tx.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    const K_HAND: &str =
+        "PokerStars Hand #7:  Hold'em No Limit ($0.01/$0.02 USD) - 2020/05/30 10:11:12 ET\n\
+         Table 'Test' 6-max Seat #1 is the button\n\
+         Seat 1: a ($2 in chips)\n\
+         Seat 2: b ($2 in chips)\n\
+         Seat 3: c ($2 in chips)\n\
+         b: posts small blind $0.01\n\
+         c: posts big blind $0.02\n\
+         *** HOLE CARDS ***\n\
+         a: raises $0.04 to $0.06\n\
+         b: folds\n\
+         c: calls $0.04\n\
+         *** FLOP *** [2c 7h Td]\n\
+         c: checks\n\
+         a: bets $0.10\n\
+         c: calls $0.10\n\
+         *** SHOW DOWN ***\n\
+         a: shows [Ah Ad] (a pair of Aces)\n\
+         c: mucks hand\n\
+         a collected $0.32 from pot\n\
+         *** SUMMARY ***\n\
+         Total pot $0.33 | Rake $0.01\n";
+
+    #[test]
+    fn hands_read_back_as_stored() {
+        let hand = Hand::parse(K_HAND).unwrap();
+        let mut store = Store::open(Path::new(":memory:")).unwrap();
+        store.insert([&hand, &hand]).unwrap();
+        let mut read = Vec::new();
+        store.for_each_hand(|h| read.push(h)).unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(format!("{:?}", read[0]), format!("{:?}", hand));
+        assert_eq!(store.outdated_hands(), Ok(0));
+    }
+
+    #[test]
+    fn older_stores_get_new_columns() {
+        let dir = TempDir::new("older_store");
+        let path = dir.join("hands.sqlite");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE hands (id INTEGER PRIMARY KEY, date TEXT NOT NULL, game TEXT NOT NULL,
+             money TEXT NOT NULL, sb INTEGER NOT NULL, bb INTEGER NOT NULL,
+             table_name TEXT NOT NULL, max_seats INTEGER NOT NULL, button INTEGER NOT NULL,
+             jackpot INTEGER NOT NULL DEFAULT 0);
+             INSERT INTO hands VALUES (1, '2020-05-30 10:11:12', 'Hold''em No Limit', 'Real',
+             1, 2, 'Test', 6, 1, 0);",
+        )
+        .unwrap();
+        drop(conn);
+
+        let store = Store::open(&path).unwrap();
+        let mut read = Vec::new();
+        store.for_each_hand(|h| read.push(h)).unwrap();
+        assert_eq!((read.len(), read[0].board.len(), read[0].pot), (1, 0, 0));
+        assert_eq!(store.outdated_hands(), Ok(1));
+
+        // columns in another order than in a new store
+        let mut store = store;
+        let hand = Hand::parse(K_HAND).unwrap();
+        store.insert([&hand]).unwrap();
+        let mut read = Vec::new();
+        store.for_each_hand(|h| read.push(h)).unwrap();
+        assert_eq!(format!("{:?}", read[1]), format!("{:?}", hand));
+    }
+}
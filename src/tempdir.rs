@@ -0,0 +1,32 @@
+// directories for tests that need files, one per test as tests run in parallel
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+// removed with its contents when dropped, even when the test fails
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(test: &str) -> TempDir {
+        let path = env::temp_dir().join(format!("rpokerhud-{}-{}", process::id(), test));
+        let _ = fs::remove_dir_all(&path); // left by a crash
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub fn join(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
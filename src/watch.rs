@@ -0,0 +1,36 @@
+// wakes the main loop up as soon as a hand history file changes (inotify on linux)
+// without it, files are still read every refresh_rate secs
+
+use crate::config::HistoryDir;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver};
+
+pub struct Watcher {
+    _watcher: RecommendedWatcher,   // stops watching when dropped
+    pub changed: Receiver<PathBuf>, // paths of files created or appended to
+}
+
+pub fn watch(dirs: &[HistoryDir]) -> notify::Result<Watcher> {
+    let (tx, rx) = channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        if let Ok(event) = event {
+            if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                for path in event.paths {
+                    let _ = tx.send(path); // main loop gone, nothing to do
+                }
+            }
+        }
+    })?;
+    for dir in dirs {
+        let mode = match dir.recursive {
+            true => RecursiveMode::Recursive,
+            false => RecursiveMode::NonRecursive,
+        };
+        watcher.watch(&dir.path, mode)?;
+    }
+    Ok(Watcher {
+        _watcher: watcher,
+        changed: rx,
+    })
+}